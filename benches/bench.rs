@@ -30,8 +30,8 @@ fn bench_create_game_srv_obj(b: &mut Bencher) {
 
 #[bench]
 fn bench_game_srv_obj_deal_fix_cards(b: &mut Bencher) {
-    let mut gs = big2rules::SrvGameState::new(8);
     b.iter(|| {
+        let mut gs = big2rules::SrvGameState::new(8);
         gs.deal(Some(&[
             0x0d00854004174000,
             0x2200000008000 | 0x201_0ab6_0100_0000,
@@ -43,8 +43,10 @@ fn bench_game_srv_obj_deal_fix_cards(b: &mut Bencher) {
 
 #[bench]
 fn bench_game_srv_obj_deal_random_cargs(b: &mut Bencher) {
-    let mut gs = big2rules::SrvGameState::new(8);
-    b.iter(|| gs.deal(None));
+    b.iter(|| {
+        let mut gs = big2rules::SrvGameState::new(8);
+        gs.deal(None)
+    });
 }
 
 #[bench]
@@ -55,7 +57,8 @@ fn bench_game_srv_obj_deal_full_play_8_rounds(b: &mut Bencher) {
 
         gs.deal(Some(
             &benchfactor::gameserver_vectors::TEST_VECTOR_CARDS_GAME1[0..4],
-        ));
+        ))
+        .unwrap();
 
         assert_eq!(gs.turn, 3);
         assert_eq!(gs.round, 1);
@@ -74,7 +77,8 @@ fn bench_game_srv_obj_deal_full_play_8_rounds(b: &mut Bencher) {
                         cp += 4;
                         gs.deal(Some(
                             &benchfactor::gameserver_vectors::TEST_VECTOR_CARDS_GAME1[cp..cp + 4],
-                        ));
+                        ))
+                        .unwrap();
                     }
                 }
                 0x000 => {
@@ -0,0 +1,324 @@
+// Matches between bots on SrvGameState, without a server. To compare strategies and to
+// catch bugs in the rules engine.
+use crate::big2rules::{self, bot::Strategy, SrvGameState};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fmt;
+
+// 1.96 standard deviations, a 95% confidence interval.
+const Z: f64 = 1.96;
+
+pub struct Arena {
+    pub rules: big2rules::rules::RuleSet,
+    pub rounds: u8,
+    // A strategy for every seat, they change seats every match.
+    pub strategies: Vec<Strategy>,
+}
+
+// How one strategy did, over all its seats.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Stats {
+    pub matches: u32,
+    pub match_wins: u32,
+    pub rounds: u32,
+    pub round_wins: u32,
+    // Rounds it paid for everyone because it didn't stop the winner.
+    pub assists: u32,
+    // Rounds it had a 2 chopped by quads or a straight flush.
+    pub chopped: u32,
+    // Of the scores at the end of the matches, for the average and its interval.
+    score_sum: f64,
+    score_squares: f64,
+}
+
+impl Stats {
+    pub fn win_rate(&self) -> f64 {
+        ratio(self.match_wins, self.matches)
+    }
+
+    // The Wilson score interval of the win rate.
+    pub fn win_rate_interval(&self) -> (f64, f64) {
+        if self.matches == 0 {
+            return (0.0, 1.0);
+        }
+        let n = self.matches as f64;
+        let p = self.win_rate();
+        let z2 = Z * Z;
+        let center = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+        let margin = Z / (1.0 + z2 / n) * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt();
+        ((center - margin).max(0.0), (center + margin).min(1.0))
+    }
+
+    pub fn avg_score(&self) -> f64 {
+        if self.matches == 0 {
+            return 0.0;
+        }
+        self.score_sum / self.matches as f64
+    }
+
+    // Half the width of the interval of the average score.
+    pub fn avg_score_margin(&self) -> f64 {
+        if self.matches < 2 {
+            return 0.0;
+        }
+        let n = self.matches as f64;
+        let mean = self.avg_score();
+        let variance = (self.score_squares - n * mean * mean) / (n - 1.0);
+        Z * (variance.max(0.0) / n).sqrt()
+    }
+
+    pub fn round_win_rate(&self) -> f64 {
+        ratio(self.round_wins, self.rounds)
+    }
+
+    pub fn assist_rate(&self) -> f64 {
+        ratio(self.assists, self.rounds)
+    }
+
+    pub fn chop_rate(&self) -> f64 {
+        ratio(self.chopped, self.rounds)
+    }
+}
+
+fn ratio(count: u32, total: u32) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    count as f64 / total as f64
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    // In the order the strategies first appear in the seats.
+    pub stats: Vec<(Strategy, Stats)>,
+    // Moves of the bots the rules engine didn't accept.
+    pub illegal_moves: u32,
+    // Rounds where the score changes don't add up to 0.
+    pub score_errors: u32,
+    // Matches the rules engine got stuck in, they are not in the stats.
+    pub aborted_matches: u32,
+}
+
+impl Report {
+    fn stats_mut(&mut self, strategy: Strategy) -> &mut Stats {
+        let i = self.stats.iter().position(|(s, _)| *s == strategy).unwrap();
+        &mut self.stats[i].1
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<10} {:>7} {:>24} {:>18} {:>10} {:>8} {:>8}",
+            "Strategy",
+            "Matches",
+            "Win rate (95% CI)",
+            "Score (95% CI)",
+            "Rounds won",
+            "Assists",
+            "Chopped"
+        )?;
+        for (strategy, stats) in self.stats.iter() {
+            let (low, high) = stats.win_rate_interval();
+            writeln!(
+                f,
+                "{:<10} {:>7} {:>24} {:>18} {:>10} {:>8} {:>8}",
+                strategy.name(),
+                stats.matches,
+                format!(
+                    "{} ({} - {})",
+                    percent(stats.win_rate()),
+                    percent(low),
+                    percent(high)
+                ),
+                format!("{:.1} ± {:.1}", stats.avg_score(), stats.avg_score_margin()),
+                percent(stats.round_win_rate()),
+                percent(stats.assist_rate()),
+                percent(stats.chop_rate()),
+            )?;
+        }
+        write!(
+            f,
+            "Illegal moves: {} Score errors: {} Aborted matches: {}",
+            self.illegal_moves, self.score_errors, self.aborted_matches
+        )
+    }
+}
+
+fn percent(rate: f64) -> String {
+    format!("{:.1}%", rate * 100.0)
+}
+
+impl Arena {
+    // Plays the matches, the same seed plays the same matches.
+    pub fn run(&self, matches: u32, seed: u64) -> Report {
+        let mut report = Report {
+            stats: Vec::new(),
+            illegal_moves: 0,
+            score_errors: 0,
+            aborted_matches: 0,
+        };
+        for &strategy in self.strategies.iter() {
+            if report.stats.iter().all(|(s, _)| *s != strategy) {
+                report.stats.push((strategy, Stats::default()));
+            }
+        }
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        let players = self.strategies.len();
+        for m in 0..matches as usize {
+            // Take turns in every seat.
+            let seats: Vec<Strategy> = (0..players)
+                .map(|p| self.strategies[(p + m) % players])
+                .collect();
+            self.play_match(&seats, rng.gen(), &mut report);
+        }
+        report
+    }
+
+    fn play_match(&self, seats: &[Strategy], seed: u64, report: &mut Report) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut bots: Vec<_> = seats.iter().map(|s| s.bot(rng.gen())).collect();
+        let mut gs = SrvGameState::with_rules(self.rounds, self.rules);
+        gs.set_seed(seed);
+
+        loop {
+            if gs.deal(None).is_err() {
+                report.aborted_matches += 1;
+                return;
+            }
+            while gs.turn >= 0 {
+                let turn = gs.turn;
+                let view = gs.view(turn as usize);
+                let ret = match bots[turn as usize].play(&view) {
+                    Some(cards) => gs.play(turn, cards),
+                    None => gs.pass(turn),
+                };
+                if ret.is_err() {
+                    report.illegal_moves += 1;
+                    // Like the server when the turn time is up.
+                    if gs.pass(turn).is_err() {
+                        let card = gs.rules.higher_single_card(0, gs.cards[turn as usize]);
+                        if gs.play(turn, card).is_err() {
+                            report.aborted_matches += 1;
+                            return;
+                        }
+                    }
+                }
+            }
+
+            let result = match gs.result {
+                Some(result) => result,
+                None => {
+                    report.aborted_matches += 1;
+                    return;
+                }
+            };
+            if result.delta_score.iter().sum::<i16>() != 0 {
+                report.score_errors += 1;
+            }
+            for (p, &strategy) in seats.iter().enumerate() {
+                let stats = report.stats_mut(strategy);
+                stats.rounds += 1;
+                if p == result.winner {
+                    stats.round_wins += 1;
+                }
+                if result.chopped[p] > 0 {
+                    stats.chopped += 1;
+                }
+                if result.assisted_by == Some(p) {
+                    stats.assists += 1;
+                }
+            }
+            if gs.is_match_over() {
+                break;
+            }
+        }
+
+        let winner = gs.winner();
+        for (p, &strategy) in seats.iter().enumerate() {
+            let score = gs.score[p] as f64;
+            let stats = report.stats_mut(strategy);
+            stats.matches += 1;
+            if winner == Some(p) {
+                stats.match_wins += 1;
+            }
+            stats.score_sum += score;
+            stats.score_squares += score * score;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arena_test() {
+        let arena = Arena {
+            rules: big2rules::rules::RuleSet::default(),
+            rounds: 2,
+            strategies: vec![
+                Strategy::Heuristic,
+                Strategy::Greedy,
+                Strategy::Random,
+                Strategy::Greedy,
+            ],
+        };
+        let report = arena.run(8, 1);
+        assert_eq!(report.illegal_moves, 0);
+        assert_eq!(report.score_errors, 0);
+        assert_eq!(report.aborted_matches, 0);
+        assert_eq!(report.stats.len(), 3);
+        assert_eq!(report.stats[0].0, Strategy::Heuristic);
+        // Greedy has two seats.
+        let greedy = report.stats[1].1;
+        assert_eq!(greedy.matches, 16);
+        assert_eq!(greedy.rounds, 32);
+        let wins: u32 = report.stats.iter().map(|(_, s)| s.match_wins).sum();
+        assert!(wins <= 8);
+        let round_wins: u32 = report.stats.iter().map(|(_, s)| s.round_wins).sum();
+        assert_eq!(round_wins, 16);
+        let (low, high) = greedy.win_rate_interval();
+        assert!(low <= greedy.win_rate() && greedy.win_rate() <= high);
+
+        // The same seed plays the same matches.
+        assert_eq!(arena.run(8, 1), report);
+        assert!(report.to_string().contains("heuristic"));
+
+        // Three players and the random bot doesn't play illegal moves either.
+        let arena = Arena {
+            rules: big2rules::rules::RuleSet {
+                players: 3,
+                ..big2rules::rules::RuleSet::STANDARD
+            },
+            rounds: 1,
+            strategies: vec![Strategy::Random, Strategy::Lowest, Strategy::Passive],
+        };
+        let report = arena.run(6, 2);
+        assert_eq!(report.illegal_moves, 0);
+        assert_eq!(report.aborted_matches, 0);
+        assert_eq!(report.stats[0].1.matches, 6);
+    }
+
+    #[test]
+    fn stats_test() {
+        let mut stats = Stats {
+            matches: 100,
+            match_wins: 50,
+            ..Stats::default()
+        };
+        let (low, high) = stats.win_rate_interval();
+        assert!((low - 0.404).abs() < 0.001, "{}", low);
+        assert!((high - 0.596).abs() < 0.001, "{}", high);
+
+        // Half the matches +10 and half -10.
+        stats.score_sum = 0.0;
+        stats.score_squares = 100.0 * 100.0;
+        assert_eq!(stats.avg_score(), 0.0);
+        let margin = stats.avg_score_margin();
+        assert!((margin - 1.96 * (10000.0f64 / 99.0 / 100.0).sqrt()).abs() < 1e-9);
+        assert_eq!(Stats::default().win_rate(), 0.0);
+    }
+}
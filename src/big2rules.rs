@@ -1,16 +1,19 @@
 use crate::network;
+use log::trace;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
 pub const RANKS: [u8; 13] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
 
 pub mod deck {
     use super::*;
     use rand::seq::SliceRandom;
-    use rand::thread_rng;
 
     pub const NUMBER_OF_CARDS: u8 = 52;
     pub const START_BIT: u8 = 12;
 
-    pub fn deal() -> [u64; 4] {
+    // Deals the seats of the rules, the other seats get no cards.
+    pub fn deal_with<R: Rng + ?Sized>(rng: &mut R, rules: &rules::RuleSet) -> [u64; 4] {
         // Create and shulle deck of cards
         let deck = {
             let mut deck = Vec::<u8>::with_capacity(52);
@@ -22,37 +25,48 @@ pub mod deck {
 
             // Randomize/shuffle the cards
             for _ in 0..256 {
-                deck.shuffle(&mut thread_rng());
+                deck.shuffle(rng);
             }
             deck
         };
-        return deal_cards(deck);
+        return deal_cards(deck, rules);
     }
-    fn deal_cards(cards: Vec<u8>) -> [u64; 4] {
+    // Two players leave the rest of the deck out. With three players the last card goes to
+    // the player with the opening card.
+    fn deal_cards(cards: Vec<u8>, rules: &rules::RuleSet) -> [u64; 4] {
         let mut players_hand: [u64; 4] = [0, 0, 0, 0];
-        let mut p: usize = 0;
-        let mut c: usize = 0;
+        let players = rules.players as usize;
+        let hand_size = rules.hand_size();
 
-        for r in cards {
-            let card_bit = 1 << r;
-            players_hand[p] |= card_bit;
-            c += 1;
-            if c == 13 {
-                // println!("p{:x} {:#08x?}", p, player_cards[p]);
-                assert!(players_hand[p].count_ones() == 13);
-                c = 0;
-                p += 1;
+        for (p, hand) in cards.chunks(hand_size).take(players).enumerate() {
+            for r in hand {
+                players_hand[p] |= 1 << r;
             }
+            assert!(players_hand[p].count_ones() as usize == hand_size);
+        }
+        if players == 3 {
+            let opening_card = rules.lowest_card().mask();
+            let p = players_hand
+                .iter()
+                .position(|&x| x & opening_card != 0)
+                .unwrap_or(0);
+            players_hand[p] |= 1 << cards[cards.len() - 1];
+        }
+        if players != 2 {
+            assert!(players_hand.iter().fold(0, |m, h| m | h) == cards::ALL);
         }
-        assert!(
-            (players_hand[0] | players_hand[1] | players_hand[2] | players_hand[3])
-                == 0xFFFF_FFFF_FFFF_F000u64
-        );
         return players_hand;
     }
 }
 
 pub mod cards {
+    use super::rules::{RuleSet, WrapStraights};
+    use std::convert::TryFrom;
+    use std::fmt;
+    use std::iter::FromIterator;
+    use std::ops;
+    use std::str::FromStr;
+
     #[non_exhaustive]
     pub struct Kind;
     #[non_exhaustive]
@@ -99,12 +113,11 @@ pub mod cards {
     }
 
     pub fn has_rank(hand: u64, rank: u64) -> u64 {
-        let mask = Kind::SUITMASK << (rank << 2);
-        return hand & mask;
+        Hand(hand).of_rank(rank).mask()
     }
 
     pub fn cnt_rank(hand: u64, rank: u64) -> u64 {
-        return has_rank(hand, rank).count_ones() as u64;
+        Hand(hand).of_rank(rank).len() as u64
     }
 
     pub fn card_selected(card: u64) -> u64 {
@@ -112,126 +125,455 @@ pub mod cards {
     }
 
     pub fn has_rank_idx(card: u64) -> u64 {
-        return card_selected(card) >> 2;
+        Hand(card).lowest().map_or(0, Card::rank)
     }
 
     pub fn has_suit(card: u64) -> u64 {
-        return 1 << (card_selected(card) & 0x3);
+        Hand(card).lowest().map_or(0, Card::suit)
     }
-}
 
-pub mod rules {
-    use super::*;
+    // All 52 cards. Bit 12 is the 3♦ and bit 63 the 2♠, the lower 12 bits are unused.
+    pub const ALL: u64 = 0xFFFF_FFFF_FFFF_F000;
 
-    #[allow(dead_code)]
-    pub fn get_numbers(hand: u64) {
-        let mut ranks: [u32; 16] = [0; 16];
-        let mut straigth: u64 = 0;
-        let mut tripps: u32 = 0;
-        let mut quads: u32 = 0;
-        let mut straigths: u32 = 0;
-        let mut doubles: u32 = 0;
+    const SUIT_BITS: u64 = 0x1111_1111_1111_1000;
+    const RANK_CHARS: &[u8; 16] = b"...3456789TJQKA2";
+    const SUIT_CHARS: &[u8; 4] = b"dchs";
 
-        for r in RANKS.iter() {
-            let idx: usize = (*r).into();
-            ranks[idx] = cards::cnt_rank(hand, idx as u64) as u32;
-            if ranks[idx] != 0 {
-                straigth |= 1 << r;
+    // A single card stored as its bit number: rank * 4 + suit. Cards order by rank, then by suit.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct Card(u8);
+
+    impl Card {
+        pub const THREE_OF_DIAMONDS: Card = Card(12);
+        pub const TWO_OF_SPADES: Card = Card(63);
+
+        // The suit is one of Kind::DIAMONDS, CLUBS, HEARTS or SPADES.
+        pub fn new(rank: u64, suit: u64) -> Option<Card> {
+            if !(Rank::THREE..=Rank::TWO).contains(&rank) {
+                return None;
             }
-            if ranks[idx] == 2 {
-                doubles += 1;
+            if suit.count_ones() != 1 || suit & !Kind::SUITMASK != 0 {
+                return None;
             }
-            if ranks[idx] == 3 {
-                tripps += 1;
+            Some(Card((rank << 2) as u8 | suit.trailing_zeros() as u8))
+        }
+
+        pub fn from_bit(bit: u32) -> Option<Card> {
+            if bit < Kind::LOWEST as u32 || bit > Kind::HIGHEST as u32 {
+                return None;
             }
-            if ranks[idx] == 4 {
-                quads += 1;
+            Some(Card(bit as u8))
+        }
+
+        // The mask must hold exactly one card.
+        pub fn from_mask(mask: u64) -> Option<Card> {
+            if mask.count_ones() != 1 {
+                return None;
             }
+            Card::from_bit(mask.trailing_zeros())
         }
-        let mut mask = 0b11111;
-        for _ in 4..16 {
-            if straigth & mask == mask {
-                straigths += 1;
+
+        pub fn bit(self) -> u32 {
+            self.0 as u32
+        }
+
+        pub fn mask(self) -> u64 {
+            1 << self.0
+        }
+
+        pub fn rank(self) -> u64 {
+            (self.0 >> 2) as u64
+        }
+
+        pub fn suit(self) -> u64 {
+            1 << (self.0 & 0x3)
+        }
+    }
+
+    impl fmt::Display for Card {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            let rank = RANK_CHARS[self.rank() as usize] as char;
+            let suit = SUIT_CHARS[(self.0 & 0x3) as usize] as char;
+            write!(f, "{}{}", rank, suit)
+        }
+    }
+
+    // Parses the Display notation, e.g. "Ts". Lowercase ranks and the ♦♣♥♠ symbols are
+    // accepted too.
+    impl FromStr for Card {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let mut chars = s.chars();
+            let (r, c) = match (chars.next(), chars.next(), chars.next()) {
+                (Some(r), Some(c), None) => (r.to_ascii_uppercase(), c),
+                _ => return Err(format!("Invalid card {}", s)),
+            };
+            let rank = RANK_CHARS.iter().rposition(|&x| x as char == r);
+            let suit = match c.to_ascii_lowercase() {
+                'd' | '♦' => Kind::DIAMONDS,
+                'c' | '♣' => Kind::CLUBS,
+                'h' | '♥' => Kind::HEARTS,
+                's' | '♠' => Kind::SPADES,
+                _ => return Err(format!("Invalid suit in card {}", s)),
+            };
+            match rank {
+                Some(rank) if rank >= Rank::THREE as usize => {
+                    Card::new(rank as u64, suit).ok_or_else(|| format!("Invalid card {}", s))
+                }
+                _ => Err(format!("Invalid rank in card {}", s)),
             }
-            mask <<= 1;
         }
-        // A2345
-        mask = 0b1100_0000_0011_1000;
-        if straigth & mask == mask {
-            straigths += 1;
-        };
-        // 23456
-        mask = 0b1000_0000_0111_1000;
-        if straigth & mask == mask {
-            straigths += 1;
-        };
+    }
 
-        let flushs = has_flush(hand);
+    // A set of cards. Hands order by their mask, so the highest card decides first.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+    pub struct Hand(u64);
 
-        let fullhouse = std::cmp::min(doubles, tripps)
-            + std::cmp::min(doubles, quads)
-            + std::cmp::min(tripps, quads);
-        println!(
-            "R{:x?} S{:16b} {:x} D{:x} T{:x} Q{:x} FH{:x} FL{:x}",
-            ranks, straigth, straigths, doubles, tripps, quads, fullhouse, flushs
-        );
+    impl Hand {
+        pub const EMPTY: Hand = Hand(0);
+        pub const ALL: Hand = Hand(ALL);
+
+        // Drops the unused lower bits, use try_from() to reject them instead.
+        pub fn from_mask(mask: u64) -> Hand {
+            Hand(mask & ALL)
+        }
+
+        pub fn mask(self) -> u64 {
+            self.0
+        }
+
+        pub fn len(self) -> usize {
+            self.0.count_ones() as usize
+        }
+
+        pub fn is_empty(self) -> bool {
+            self.0 == 0
+        }
+
+        pub fn contains(self, card: Card) -> bool {
+            self.0 & card.mask() != 0
+        }
+
+        pub fn insert(&mut self, card: Card) {
+            self.0 |= card.mask();
+        }
+
+        pub fn remove(&mut self, card: Card) {
+            self.0 &= !card.mask();
+        }
+
+        pub fn lowest(self) -> Option<Card> {
+            self.iter().next()
+        }
+
+        pub fn highest(self) -> Option<Card> {
+            self.iter().next_back()
+        }
+
+        pub fn of_rank(self, rank: u64) -> Hand {
+            Hand(self.0 & (Kind::SUITMASK << (rank << 2)))
+        }
+
+        pub fn of_suit(self, suit: u64) -> Hand {
+            Hand(self.0 & (SUIT_BITS << suit.trailing_zeros()))
+        }
+
+        // True when all cards have the same suit.
+        pub fn is_flush(self) -> bool {
+            (0..4).any(|s| self.of_suit(1 << s) == self)
+        }
+
+        // The cards from low to high.
+        pub fn iter(self) -> Cards {
+            Cards(self.0)
+        }
+
+        pub fn combo(self) -> Option<Combo> {
+            Combo::new(self)
+        }
     }
-    pub fn is_valid_hand(hand: u64) -> bool {
-        // Check cards range. Only the upper 52 bits are used.
-        let ret: bool = (hand & 0xFFF) == 0;
 
-        // Check number of cards played. count = 1, 2, 3 or 5 is valid.
-        let cardcount = hand.count_ones();
-        ret && cardcount != 4 && cardcount < 6 && cardcount != 0
+    impl TryFrom<u64> for Hand {
+        type Error = &'static str;
+
+        fn try_from(mask: u64) -> Result<Self, Self::Error> {
+            if mask & !ALL != 0 {
+                return Err("Invalid cards!");
+            }
+            Ok(Hand(mask))
+        }
     }
-    #[allow(dead_code)]
-    pub fn beter_hand(board: u64, hand: u64) -> bool {
-        if is_valid_hand(hand) == false {
-            return false;
+
+    impl From<Card> for Hand {
+        fn from(card: Card) -> Self {
+            Hand(card.mask())
         }
+    }
 
-        let card_cnt_hand = hand.count_ones();
-        let card_cnt_board = board.count_ones();
+    impl fmt::Display for Hand {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            for (i, card) in self.iter().enumerate() {
+                if i != 0 {
+                    write!(f, " ")?;
+                }
+                write!(f, "{}", card)?;
+            }
+            Ok(())
+        }
+    }
 
-        // Board and hand count must match.
-        // Board count 0 means new turn.
-        if card_cnt_board != 0 && card_cnt_board != card_cnt_hand {
-            return false;
+    // Parses cards separated by whitespace, e.g. "3d 4d 5h Ts As 2s". The order doesn't
+    // matter, a card can't be listed twice.
+    impl FromStr for Hand {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let mut hand = Hand::EMPTY;
+            for card in s.split_whitespace() {
+                let card: Card = card.parse()?;
+                if hand.contains(card) {
+                    return Err(format!("Card {} is listed twice", card));
+                }
+                hand.insert(card);
+            }
+            Ok(hand)
         }
-        return true;
     }
 
-    pub fn higher_single_card(board: u64, hand: u64) -> u64 {
-        let mask: u64 = u64::MAX.wrapping_shl(board.trailing_zeros());
-        let higher_cards = hand & mask;
-        let mask: u64 = 1u64.wrapping_shl(higher_cards.trailing_zeros());
-        let better_card = hand & mask;
+    impl FromIterator<Card> for Hand {
+        fn from_iter<I: IntoIterator<Item = Card>>(iter: I) -> Self {
+            let mut hand = Hand::EMPTY;
+            for card in iter {
+                hand.insert(card);
+            }
+            hand
+        }
+    }
+
+    impl IntoIterator for Hand {
+        type Item = Card;
+        type IntoIter = Cards;
 
-        better_card
+        fn into_iter(self) -> Cards {
+            self.iter()
+        }
     }
 
-    pub fn is_flush(hand: u64) -> bool {
-        let mut mask: u64 = 0x1111_1111_1111_1000;
-        for _ in 0..4 {
-            if (hand & !mask) == 0 {
-                return true;
+    // Iterator over the cards of a hand.
+    #[derive(Debug, Clone)]
+    pub struct Cards(u64);
+
+    impl Iterator for Cards {
+        type Item = Card;
+
+        fn next(&mut self) -> Option<Card> {
+            if self.0 == 0 {
+                return None;
             }
-            mask <<= 1;
+            let bit = self.0.trailing_zeros();
+            self.0 &= self.0 - 1;
+            Some(Card(bit as u8))
+        }
+
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            let len = self.0.count_ones() as usize;
+            (len, Some(len))
         }
-        return false;
     }
-    pub fn has_flush(hand: u64) -> u8 {
-        let mut mask: u64 = 0x1111_1111_1111_1000;
-        let mut flushs: u8 = 0;
-        for _ in 0..4 {
-            if (hand & mask).count_ones() >= 5 {
-                flushs += 1;
+
+    impl DoubleEndedIterator for Cards {
+        fn next_back(&mut self) -> Option<Card> {
+            if self.0 == 0 {
+                return None;
             }
-            mask <<= 1;
+            let bit = 63 - self.0.leading_zeros();
+            self.0 &= !(1 << bit);
+            Some(Card(bit as u8))
         }
-        return flushs;
     }
-    pub fn score_hand(hand: u64) -> u64 {
+
+    impl ExactSizeIterator for Cards {}
+
+    impl ops::BitOr for Hand {
+        type Output = Hand;
+        fn bitor(self, rhs: Hand) -> Hand {
+            Hand(self.0 | rhs.0)
+        }
+    }
+
+    impl ops::BitAnd for Hand {
+        type Output = Hand;
+        fn bitand(self, rhs: Hand) -> Hand {
+            Hand(self.0 & rhs.0)
+        }
+    }
+
+    impl ops::BitXor for Hand {
+        type Output = Hand;
+        fn bitxor(self, rhs: Hand) -> Hand {
+            Hand(self.0 ^ rhs.0)
+        }
+    }
+
+    impl ops::Sub for Hand {
+        type Output = Hand;
+        fn sub(self, rhs: Hand) -> Hand {
+            Hand(self.0 & !rhs.0)
+        }
+    }
+
+    impl ops::Not for Hand {
+        type Output = Hand;
+        fn not(self) -> Hand {
+            Hand(!self.0 & ALL)
+        }
+    }
+
+    impl ops::BitOrAssign for Hand {
+        fn bitor_assign(&mut self, rhs: Hand) {
+            self.0 |= rhs.0;
+        }
+    }
+
+    impl ops::BitAndAssign for Hand {
+        fn bitand_assign(&mut self, rhs: Hand) {
+            self.0 &= rhs.0;
+        }
+    }
+
+    impl ops::BitXorAssign for Hand {
+        fn bitxor_assign(&mut self, rhs: Hand) {
+            self.0 ^= rhs.0;
+        }
+    }
+
+    impl ops::SubAssign for Hand {
+        fn sub_assign(&mut self, rhs: Hand) {
+            self.0 &= !rhs.0;
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub enum ComboKind {
+        One,
+        Pair,
+        Set,
+        Straight,
+        Flush,
+        FullHouse,
+        Quads,
+        StraightFlush,
+    }
+
+    impl ComboKind {
+        fn from_score(score: u64) -> ComboKind {
+            match score & Kind::TYPE {
+                Kind::ONE => ComboKind::One,
+                Kind::PAIR => ComboKind::Pair,
+                Kind::SET => ComboKind::Set,
+                Kind::STRAIGHT => ComboKind::Straight,
+                Kind::FLUSH => ComboKind::Flush,
+                Kind::FULLHOUSE => ComboKind::FullHouse,
+                Kind::QUADS => ComboKind::Quads,
+                _ => ComboKind::StraightFlush,
+            }
+        }
+
+        // Number of cards in this kind of combo.
+        pub fn card_count(self) -> usize {
+            match self {
+                ComboKind::One => 1,
+                ComboKind::Pair => 2,
+                ComboKind::Set => 3,
+                _ => 5,
+            }
+        }
+    }
+
+    impl fmt::Display for ComboKind {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            let name = match self {
+                ComboKind::One => "One",
+                ComboKind::Pair => "Pair",
+                ComboKind::Set => "Set",
+                ComboKind::Straight => "Straight",
+                ComboKind::Flush => "Flush",
+                ComboKind::FullHouse => "Full House",
+                ComboKind::Quads => "Quads",
+                ComboKind::StraightFlush => "Straight Flush",
+            };
+            write!(f, "{}", name)
+        }
+    }
+
+    // A hand that can be played, together with its score. Combos order by score, which
+    // only says which one wins when both have the same number of cards.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct Combo {
+        score: u64,
+        hand: Hand,
+    }
+
+    impl Combo {
+        pub fn new(hand: Hand) -> Option<Combo> {
+            Combo::with_rules(hand, &RuleSet::STANDARD)
+        }
+
+        // Only combos scored with the same rules can be compared.
+        pub fn with_rules(hand: Hand, rules: &RuleSet) -> Option<Combo> {
+            let score = score(hand.mask(), rules);
+            if score == 0 {
+                return None;
+            }
+            Some(Combo { score, hand })
+        }
+
+        // See score() for the layout.
+        pub fn score(self) -> u64 {
+            self.score
+        }
+
+        pub fn hand(self) -> Hand {
+            self.hand
+        }
+
+        pub fn kind(self) -> ComboKind {
+            ComboKind::from_score(self.score)
+        }
+
+        pub fn card_count(self) -> usize {
+            self.hand.len()
+        }
+
+        // True when this combo can be played on top of the other one.
+        pub fn beats(self, other: Combo) -> bool {
+            self.card_count() == other.card_count() && self.score > other.score
+        }
+
+        // Quads and straight flushes.
+        pub fn is_bomb(self) -> bool {
+            matches!(self.kind(), ComboKind::Quads | ComboKind::StraightFlush)
+        }
+
+        // Nothing can beat it: the highest 2, a pair with the highest 2 or a set of 2s.
+        // See RuleSet::is_highest for the rules with bombs.
+        pub fn is_highest(self) -> bool {
+            match self.kind() {
+                ComboKind::One | ComboKind::Pair => self.score & 0xFF == Kind::HIGHEST,
+                ComboKind::Set => self.score & 0xFF == Rank::TWO,
+                _ => false,
+            }
+        }
+    }
+
+    impl fmt::Display for Combo {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{} {}", self.kind(), self.hand)
+        }
+    }
+
+    fn score(hand: u64, rules: &RuleSet) -> u64 {
         // Score:
         //  0xKNN = One, Pair and Straigth, Flush
         //    |++- highest card: bit nummer of the highest card
@@ -242,10 +584,13 @@ pub mod rules {
         //    |+-- Zero
         //        +--- Kind: Kind::QUADS or Kind::SET or Kind::FULLHOUSE
 
-        if is_valid_hand(hand) == false {
+        // The card bit numbers follow the suit order of the rules.
+
+        let card_cnt_hand: u64 = hand.count_ones().into();
+        if hand & !ALL != 0 || !matches!(card_cnt_hand, 1 | 2 | 3 | 5) {
             return 0;
         }
-        let card_cnt_hand: u64 = hand.count_ones().into();
+        let hand = rules.remap(hand);
 
         // find the highest card and calc the rank.
         let highest_card: u64 = 63 - hand.leading_zeros() as u64;
@@ -263,13 +608,13 @@ pub mod rules {
             }
 
             if card_cnt_hand == 1 {
-                return cards::Kind::ONE | highest_card;
+                return Kind::ONE | highest_card;
             }
             if card_cnt_hand == 2 {
-                return cards::Kind::PAIR | highest_card;
+                return Kind::PAIR | highest_card;
             }
             if card_cnt_hand == 3 {
-                return cards::Kind::SET | rank;
+                return Kind::SET | rank;
             }
 
             return 0;
@@ -278,28 +623,28 @@ pub mod rules {
         let lowest_card: u64 = hand.trailing_zeros() as u64;
         let low_rank: u64 = lowest_card >> 2;
         // Get the played suit of that rank.
-        let low_suitmask = hand >> (low_rank << 2) & cards::Kind::SUITMASK;
+        let low_suitmask = hand >> (low_rank << 2) & Kind::SUITMASK;
         // Count number of cards based on the suit
         let low_cnt: u64 = low_suitmask.count_ones() as u64;
 
         // Quad
         if cnt == 4 {
-            return cards::Kind::QUADS | rank;
+            return Kind::QUADS | rank;
         }
         if low_cnt == 4 {
-            return cards::Kind::QUADS | low_rank;
+            return Kind::QUADS | low_rank;
         }
 
         // Full House
         if cnt == 3 && low_cnt == 2 {
-            return cards::Kind::FULLHOUSE | rank;
+            return Kind::FULLHOUSE | rank;
         }
         if cnt == 2 && low_cnt == 3 {
-            return cards::Kind::FULLHOUSE | low_rank;
+            return Kind::FULLHOUSE | low_rank;
         }
 
         // Flush
-        let is_flush: bool = is_flush(hand);
+        let is_flush: bool = Hand(hand).is_flush();
 
         // Straigth detection
         let mut is_straight: bool = rank - low_rank == 4 || rank - low_rank == 12;
@@ -307,24 +652,36 @@ pub mod rules {
         if is_straight {
             let mut straigth_score: u64 = 0;
             if rank - low_rank == 12 {
-                is_straight = cards::has_rank(hand, cards::Rank::THREE) != 0
-                    && cards::has_rank(hand, cards::Rank::FOUR) != 0
-                    && cards::has_rank(hand, cards::Rank::FIVE) != 0
-                    && cards::has_rank(hand, cards::Rank::TWO) != 0;
+                is_straight = has_rank(hand, Rank::THREE) != 0
+                    && has_rank(hand, Rank::FOUR) != 0
+                    && has_rank(hand, Rank::FIVE) != 0
+                    && has_rank(hand, Rank::TWO) != 0
+                    && rules.twos_in_straights;
+                // Lowest wrapping straights rank by the highest 5 or 6.
+                let top = |r: u64| 63 - has_rank(hand, r).leading_zeros() as u64;
                 // Straight 23456
-                if is_straight && cards::has_rank(hand, cards::Rank::SIX) != 0 {
-                    straigth_score |= highest_card | 0x40;
+                if is_straight && has_rank(hand, Rank::SIX) != 0 {
+                    straigth_score = match rules.wrap_straights {
+                        WrapStraights::Highest => highest_card | 0x40,
+                        WrapStraights::Lowest => top(Rank::SIX),
+                        WrapStraights::Disallowed => 0,
+                    };
                 }
                 // Straight A2345
-                if is_straight && cards::has_rank(hand, cards::Rank::ACE) != 0 {
-                    straigth_score |= highest_card | 0x80;
+                if is_straight && has_rank(hand, Rank::ACE) != 0 {
+                    straigth_score = match rules.wrap_straights {
+                        WrapStraights::Highest => highest_card | 0x80,
+                        WrapStraights::Lowest => top(Rank::FIVE),
+                        WrapStraights::Disallowed => 0,
+                    };
                 }
             } else {
-                is_straight = cards::has_rank(hand, low_rank) != 0
-                    && cards::has_rank(hand, low_rank + 1) != 0
-                    && cards::has_rank(hand, low_rank + 2) != 0
-                    && cards::has_rank(hand, low_rank + 3) != 0
-                    && cards::has_rank(hand, low_rank + 4) != 0;
+                is_straight = has_rank(hand, low_rank) != 0
+                    && has_rank(hand, low_rank + 1) != 0
+                    && has_rank(hand, low_rank + 2) != 0
+                    && has_rank(hand, low_rank + 3) != 0
+                    && has_rank(hand, low_rank + 4) != 0
+                    && (rank != Rank::TWO || rules.twos_in_straights);
                 if is_straight {
                     straigth_score = highest_card;
                 }
@@ -334,44 +691,1495 @@ pub mod rules {
 
             if is_straight {
                 if is_flush {
-                    return cards::Kind::STRAIGHTFLUSH | straigth_score;
+                    return Kind::STRAIGHTFLUSH | straigth_score;
+                }
+                return Kind::STRAIGHT | straigth_score;
+            }
+        }
+
+        if !is_straight && is_flush {
+            if rules.flush_by_suit {
+                return Kind::FLUSH | (highest_card & 0x3) << 4 | rank;
+            }
+            return Kind::FLUSH | highest_card;
+        }
+
+        return 0;
+    }
+}
+
+pub mod rules {
+    use super::*;
+    use std::convert::TryFrom;
+    use std::fmt;
+    use std::str::FromStr;
+
+    // Order of the suits from low to high.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum SuitOrder {
+        // ♦ < ♣ < ♥ < ♠
+        DiamondsClubsHeartsSpades,
+        // ♣ < ♦ < ♥ < ♠
+        ClubsDiamondsHeartsSpades,
+        // ♠ < ♣ < ♦ < ♥
+        SpadesClubsDiamondsHearts,
+    }
+
+    impl SuitOrder {
+        // Position of each suit, in the order of the suit bits: ♦, ♣, ♥ and ♠.
+        fn positions(self) -> [u64; 4] {
+            match self {
+                SuitOrder::DiamondsClubsHeartsSpades => [0, 1, 2, 3],
+                SuitOrder::ClubsDiamondsHeartsSpades => [1, 0, 2, 3],
+                SuitOrder::SpadesClubsDiamondsHearts => [2, 1, 3, 0],
+            }
+        }
+    }
+
+    // Straights that go round the 2: A-2-3-4-5 and 2-3-4-5-6.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum WrapStraights {
+        // Above all other straights, A-2-3-4-5 is the highest.
+        Highest,
+        // Below all other straights, they rank by their 5 and 6.
+        Lowest,
+        // Not a straight.
+        Disallowed,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct RuleSet {
+        pub suit_order: SuitOrder,
+        pub wrap_straights: WrapStraights,
+        // J-Q-K-A-2 and the straights that go round the 2 are straights.
+        pub twos_in_straights: bool,
+        // Flushes compare the suit first and the highest card second.
+        pub flush_by_suit: bool,
+        // A five-card hand beats the lower kinds of five-card hands, like a full house on a
+        // straight. Without it only the same kind can be played on a five-card hand.
+        pub mixed_five_card_kinds: bool,
+        // A dealt hand with an InstantWin wins the round before the first play.
+        pub instant_wins: bool,
+        // Quads and straight flushes are bombs, they beat any single and pair.
+        pub bombs: bool,
+        // Seats at the table, 2 to 4. Fewer players get bigger hands, see hand_size.
+        pub players: u8,
+    }
+
+    impl RuleSet {
+        pub const STANDARD: RuleSet = RuleSet {
+            suit_order: SuitOrder::DiamondsClubsHeartsSpades,
+            wrap_straights: WrapStraights::Highest,
+            twos_in_straights: true,
+            flush_by_suit: false,
+            mixed_five_card_kinds: true,
+            instant_wins: false,
+            bombs: false,
+            players: 4,
+        };
+
+        pub const PRESETS: [(&'static str, RuleSet); 9] = [
+            ("standard", RuleSet::STANDARD),
+            (
+                "clubs-low",
+                RuleSet {
+                    suit_order: SuitOrder::ClubsDiamondsHeartsSpades,
+                    ..RuleSet::STANDARD
+                },
+            ),
+            (
+                "spades-low",
+                RuleSet {
+                    suit_order: SuitOrder::SpadesClubsDiamondsHearts,
+                    ..RuleSet::STANDARD
+                },
+            ),
+            (
+                "suit-flush",
+                RuleSet {
+                    flush_by_suit: true,
+                    ..RuleSet::STANDARD
+                },
+            ),
+            (
+                "low-wrap",
+                RuleSet {
+                    wrap_straights: WrapStraights::Lowest,
+                    ..RuleSet::STANDARD
+                },
+            ),
+            (
+                "no-twos",
+                RuleSet {
+                    wrap_straights: WrapStraights::Disallowed,
+                    twos_in_straights: false,
+                    ..RuleSet::STANDARD
+                },
+            ),
+            (
+                "same-kind",
+                RuleSet {
+                    mixed_five_card_kinds: false,
+                    ..RuleSet::STANDARD
+                },
+            ),
+            (
+                "instant-win",
+                RuleSet {
+                    instant_wins: true,
+                    ..RuleSet::STANDARD
+                },
+            ),
+            (
+                "bombs",
+                RuleSet {
+                    bombs: true,
+                    ..RuleSet::STANDARD
+                },
+            ),
+        ];
+
+        pub fn preset(name: &str) -> Option<RuleSet> {
+            RuleSet::PRESETS
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, rules)| *rules)
+        }
+
+        // Name of the preset, "custom" when the rules don't match one. The presets are for
+        // any number of players.
+        pub fn name(&self) -> &'static str {
+            let rules = RuleSet {
+                players: 4,
+                ..*self
+            };
+            RuleSet::PRESETS
+                .iter()
+                .find(|(_, preset)| *preset == rules)
+                .map_or("custom", |(n, _)| n)
+        }
+
+        // Cards dealt to every player. Four and two players get 13 cards, three players
+        // 17 cards and the player with the opening card gets the last card too.
+        pub fn hand_size(&self) -> usize {
+            if self.players == 3 {
+                17
+            } else {
+                13
+            }
+        }
+
+        // Rules that can't be played together. The instant wins are for 13 cards, three
+        // players get 17 or 18 cards.
+        pub fn check(&self) -> Result<(), String> {
+            if self.instant_wins && self.hand_size() != 13 {
+                return Err(format!(
+                    "Instant wins need 13 cards, not {}",
+                    self.hand_size()
+                ));
+            }
+            Ok(())
+        }
+
+        // The rule set packed in 11 bits for the RulesMessage.
+        pub fn to_bits(self) -> u32 {
+            let suit_order = match self.suit_order {
+                SuitOrder::DiamondsClubsHeartsSpades => 0,
+                SuitOrder::ClubsDiamondsHeartsSpades => 1,
+                SuitOrder::SpadesClubsDiamondsHearts => 2,
+            };
+            let wrap_straights = match self.wrap_straights {
+                WrapStraights::Highest => 0,
+                WrapStraights::Lowest => 1,
+                WrapStraights::Disallowed => 2,
+            };
+            suit_order
+                | wrap_straights << 2
+                | (self.twos_in_straights as u32) << 4
+                | (self.flush_by_suit as u32) << 5
+                | (self.mixed_five_card_kinds as u32) << 6
+                | (self.bombs as u32) << 7
+                | (self.instant_wins as u32) << 8
+                | (4 - self.players as u32) << 9
+        }
+
+        pub fn from_bits(bits: u32) -> Option<RuleSet> {
+            let suit_order = match bits & 0x3 {
+                0 => SuitOrder::DiamondsClubsHeartsSpades,
+                1 => SuitOrder::ClubsDiamondsHeartsSpades,
+                2 => SuitOrder::SpadesClubsDiamondsHearts,
+                _ => return None,
+            };
+            let wrap_straights = match bits >> 2 & 0x3 {
+                0 => WrapStraights::Highest,
+                1 => WrapStraights::Lowest,
+                2 => WrapStraights::Disallowed,
+                _ => return None,
+            };
+            let players = match bits >> 9 & 0x3 {
+                0 => 4,
+                1 => 3,
+                2 => 2,
+                _ => return None,
+            };
+            if bits >> 11 != 0 {
+                return None;
+            }
+            Some(RuleSet {
+                suit_order,
+                wrap_straights,
+                twos_in_straights: bits & 0x10 != 0,
+                flush_by_suit: bits & 0x20 != 0,
+                mixed_five_card_kinds: bits & 0x40 != 0,
+                instant_wins: bits & 0x100 != 0,
+                bombs: bits & 0x80 != 0,
+                players,
+            })
+        }
+
+        // Bit number of the card when the suits are put in this order. Rank first, then suit.
+        pub fn card_value(&self, card: cards::Card) -> u64 {
+            let suit = card.suit().trailing_zeros() as usize;
+            card.rank() << 2 | self.suit_order.positions()[suit]
+        }
+
+        // The lowest card of the deck, it opens the match.
+        pub fn lowest_card(&self) -> cards::Card {
+            let positions = self.suit_order.positions();
+            let suit = positions.iter().position(|&p| p == 0).unwrap();
+            cards::Card::new(cards::Rank::THREE, 1 << suit).unwrap()
+        }
+
+        // Moves every card to the bit of its value, so the standard order applies.
+        pub fn remap(&self, hand: u64) -> u64 {
+            if self.suit_order == SuitOrder::DiamondsClubsHeartsSpades {
+                return hand;
+            }
+            cards::Hand::from_mask(hand)
+                .iter()
+                .fold(0, |m, card| m | 1 << self.card_value(card))
+        }
+
+        // The lowest card in the hand higher than the single card on the board. Any card
+        // is higher than an empty board. Returns 0 when there is none.
+        pub fn higher_single_card(&self, board: u64, hand: u64) -> u64 {
+            let board = cards::Hand::from_mask(board)
+                .iter()
+                .map(|card| self.card_value(card))
+                .max();
+            cards::Hand::from_mask(hand)
+                .iter()
+                .filter(|&card| !matches!(board, Some(b) if self.card_value(card) <= b))
+                .min_by_key(|&card| self.card_value(card))
+                .map_or(0, cards::Card::mask)
+        }
+
+        // Both combos must be scored with these rules.
+        pub fn beats(&self, combo: cards::Combo, board: cards::Combo) -> bool {
+            if self.is_bomb_on(combo, board) {
+                return true;
+            }
+            let same_kind = combo.kind() == board.kind();
+            combo.beats(board)
+                && (self.mixed_five_card_kinds || board.card_count() != 5 || same_kind)
+        }
+
+        // A bomb played on a single or a pair, it doesn't need the same number of cards.
+        pub fn is_bomb_on(&self, combo: cards::Combo, board: cards::Combo) -> bool {
+            self.bombs && combo.is_bomb() && board.card_count() <= 2
+        }
+
+        // Nothing can beat the combo. With bombs a single or pair can always be bombed.
+        pub fn is_highest(&self, combo: cards::Combo) -> bool {
+            combo.is_highest() && !(self.bombs && combo.card_count() <= 2)
+        }
+
+        // True when the hand can be played on the board, an empty board takes any combo.
+        pub fn can_play(&self, board: u64, hand: u64) -> bool {
+            let combo = match cards::Combo::with_rules(cards::Hand::from_mask(hand), self) {
+                None => return false,
+                Some(combo) => combo,
+            };
+            match cards::Combo::with_rules(cards::Hand::from_mask(board), self) {
+                None => board == 0,
+                Some(board) => self.beats(combo, board),
+            }
+        }
+    }
+
+    // A dealt hand that wins the round at once. From the highest to the lowest, the highest
+    // one wins when more players have one.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+    pub enum InstantWin {
+        // One card of every rank, 3 to 2.
+        Dragon = 1,
+        FourTwos = 2,
+        // Three sets, a quad counts as a set.
+        ThreeTriples = 3,
+        // Six pairs, a set counts as a pair and a quad as two pairs.
+        SixPairs = 4,
+    }
+
+    impl InstantWin {
+        // Checks the 13 cards of a dealt hand.
+        pub fn of_hand(hand: u64) -> Option<InstantWin> {
+            let hand = cards::Hand::from_mask(hand);
+            if hand.len() != 13 {
+                return None;
+            }
+            let counts: Vec<usize> = RANKS
+                .iter()
+                .map(|&r| hand.of_rank(r as u64).len())
+                .collect();
+            if counts.iter().all(|&c| c == 1) {
+                return Some(InstantWin::Dragon);
+            }
+            if hand.of_rank(cards::Rank::TWO).len() == 4 {
+                return Some(InstantWin::FourTwos);
+            }
+            if counts.iter().filter(|&&c| c >= 3).count() >= 3 {
+                return Some(InstantWin::ThreeTriples);
+            }
+            if counts.iter().map(|c| c / 2).sum::<usize>() >= 6 {
+                return Some(InstantWin::SixPairs);
+            }
+            None
+        }
+
+        // Number for the StateMessageAction, 0 is no instant win.
+        pub fn to_byte(self) -> u8 {
+            self as u8
+        }
+
+        pub fn from_byte(byte: u8) -> Option<InstantWin> {
+            match byte {
+                1 => Some(InstantWin::Dragon),
+                2 => Some(InstantWin::FourTwos),
+                3 => Some(InstantWin::ThreeTriples),
+                4 => Some(InstantWin::SixPairs),
+                _ => None,
+            }
+        }
+    }
+
+    impl fmt::Display for InstantWin {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            let s = match self {
+                InstantWin::Dragon => "a dragon",
+                InstantWin::FourTwos => "four twos",
+                InstantWin::ThreeTriples => "three triples",
+                InstantWin::SixPairs => "six pairs",
+            };
+            write!(f, "{}", s)
+        }
+    }
+
+    impl Default for RuleSet {
+        fn default() -> Self {
+            RuleSet::STANDARD
+        }
+    }
+
+    impl FromStr for RuleSet {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            RuleSet::preset(s).ok_or_else(|| format!("Unknown rules {}", s))
+        }
+    }
+
+    impl fmt::Display for RuleSet {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{}", self.name())?;
+            if self.players != 4 {
+                write!(f, ", {} players", self.players)?;
+            }
+            Ok(())
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn get_numbers(hand: u64) {
+        let mut ranks: [u32; 16] = [0; 16];
+        let mut straigth: u64 = 0;
+        let mut tripps: u32 = 0;
+        let mut quads: u32 = 0;
+        let mut straigths: u32 = 0;
+        let mut doubles: u32 = 0;
+
+        for r in RANKS.iter() {
+            let idx: usize = (*r).into();
+            ranks[idx] = cards::cnt_rank(hand, idx as u64) as u32;
+            if ranks[idx] != 0 {
+                straigth |= 1 << r;
+            }
+            if ranks[idx] == 2 {
+                doubles += 1;
+            }
+            if ranks[idx] == 3 {
+                tripps += 1;
+            }
+            if ranks[idx] == 4 {
+                quads += 1;
+            }
+        }
+        let mut mask = 0b11111;
+        for _ in 4..16 {
+            if straigth & mask == mask {
+                straigths += 1;
+            }
+            mask <<= 1;
+        }
+        // A2345
+        mask = 0b1100_0000_0011_1000;
+        if straigth & mask == mask {
+            straigths += 1;
+        };
+        // 23456
+        mask = 0b1000_0000_0111_1000;
+        if straigth & mask == mask {
+            straigths += 1;
+        };
+
+        let flushs = has_flush(hand);
+
+        let fullhouse = std::cmp::min(doubles, tripps)
+            + std::cmp::min(doubles, quads)
+            + std::cmp::min(tripps, quads);
+        println!(
+            "R{:x?} S{:16b} {:x} D{:x} T{:x} Q{:x} FH{:x} FL{:x}",
+            ranks, straigth, straigths, doubles, tripps, quads, fullhouse, flushs
+        );
+    }
+    pub fn is_valid_hand(hand: u64) -> bool {
+        // Only the upper 52 bits are used and 1, 2, 3 or 5 cards can be played.
+        match cards::Hand::try_from(hand) {
+            Ok(hand) => matches!(hand.len(), 1 | 2 | 3 | 5),
+            Err(_) => false,
+        }
+    }
+    #[allow(dead_code)]
+    pub fn beter_hand(board: u64, hand: u64) -> bool {
+        if is_valid_hand(hand) == false {
+            return false;
+        }
+
+        let card_cnt_hand = hand.count_ones();
+        let card_cnt_board = board.count_ones();
+
+        // Board and hand count must match.
+        // Board count 0 means new turn.
+        if card_cnt_board != 0 && card_cnt_board != card_cnt_hand {
+            return false;
+        }
+        return true;
+    }
+
+    pub fn higher_single_card(board: u64, hand: u64) -> u64 {
+        RuleSet::STANDARD.higher_single_card(board, hand)
+    }
+
+    // Every combo from the hand that can be played on the board, lowest score first.
+    // An empty board is a new lead, then every combo of 1, 2, 3 or 5 cards is legal.
+    pub fn legal_moves(board: u64, hand: u64) -> Vec<cards::Combo> {
+        legal_moves_with(board, hand, &RuleSet::STANDARD)
+    }
+
+    pub fn legal_moves_with(board: u64, hand: u64, rules: &RuleSet) -> Vec<cards::Combo> {
+        let hand = cards::Hand::from_mask(hand);
+        let mut moves = Vec::new();
+
+        let board = match cards::Combo::with_rules(cards::Hand::from_mask(board), rules) {
+            Some(board) => Some(board),
+            None if board == 0 => None,
+            None => return moves,
+        };
+        let sizes: &[usize] = match board {
+            Some(board) if rules.bombs && board.card_count() <= 2 => &[board.card_count(), 5],
+            Some(board) => &[board.card_count()][..],
+            None => &[1, 2, 3, 5],
+        };
+
+        for &size in sizes {
+            if size > 3 {
+                let cards: Vec<cards::Card> = hand.iter().collect();
+                pick_combos(rules, &cards, size, cards::Hand::EMPTY, &mut moves);
+                continue;
+            }
+            // Singles, pairs and sets are all of one rank.
+            for rank in cards::Rank::THREE..=cards::Rank::TWO {
+                let cards: Vec<cards::Card> = hand.of_rank(rank).iter().collect();
+                pick_combos(rules, &cards, size, cards::Hand::EMPTY, &mut moves);
+            }
+        }
+
+        if let Some(board) = board {
+            moves.retain(|&c| rules.beats(c, board));
+        }
+        moves.sort();
+        moves
+    }
+
+    // Adds the valid combos of `size` cards made of `picked` plus cards from `cards`.
+    fn pick_combos(
+        rules: &RuleSet,
+        cards: &[cards::Card],
+        size: usize,
+        picked: cards::Hand,
+        moves: &mut Vec<cards::Combo>,
+    ) {
+        let missing = size - picked.len();
+        if missing == 0 {
+            if let Some(combo) = cards::Combo::with_rules(picked, rules) {
+                moves.push(combo);
+            }
+            return;
+        }
+        for (i, &card) in cards.iter().enumerate() {
+            if cards.len() - i < missing {
+                break;
+            }
+            let mut next = picked;
+            next.insert(card);
+            pick_combos(rules, &cards[i + 1..], size, next, moves);
+        }
+    }
+
+    pub fn is_flush(hand: u64) -> bool {
+        cards::Hand::from_mask(hand).is_flush()
+    }
+    pub fn has_flush(hand: u64) -> u8 {
+        let hand = cards::Hand::from_mask(hand);
+        let suits = [
+            cards::Kind::DIAMONDS,
+            cards::Kind::CLUBS,
+            cards::Kind::HEARTS,
+            cards::Kind::SPADES,
+        ];
+        suits
+            .iter()
+            .filter(|&&s| hand.of_suit(s).len() >= 5)
+            .count() as u8
+    }
+    pub fn score_hand(hand: u64) -> u64 {
+        score_hand_with(hand, &RuleSet::STANDARD)
+    }
+
+    // Scores of different rule sets can't be compared.
+    pub fn score_hand_with(hand: u64, rules: &RuleSet) -> u64 {
+        match cards::Hand::try_from(hand) {
+            Ok(hand) => cards::Combo::with_rules(hand, rules).map_or(0, |c| c.score()),
+            Err(_) => 0,
+        }
+    }
+}
+
+pub struct GameState {
+    pub sm: network::StateMessage,
+    pub srn: std::io::Stdout,
+    pub board: u64,
+    pub board_score: u64,
+    pub cards_selected: u64,
+    pub auto_pass: bool,
+    pub i_am_ready: bool,
+    pub is_valid_hand: bool,
+    pub hand_score: u64,
+    // End of the turn time of the current player, None when there is no time limit.
+    pub turn_deadline: Option<std::time::Instant>,
+    // The rules announced by the server.
+    pub rules: rules::RuleSet,
+    // Your cards, from the StateMessage or the HandMessage when there are more than 13.
+    pub hand: u64,
+    // The cards played this round, from the PLAY actions. Only the plays since you joined.
+    pub played_cards: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SrvAction {
+    Update,
+    Deal,
+    // The deal gave `player` an instant win, the round is over.
+    InstantWin {
+        player: i32,
+        win: rules::InstantWin,
+    },
+    // board: the cards on the board before the action.
+    Play {
+        player: i32,
+        hand: u64,
+        board: u64,
+        is_end_of_cycle: bool,
+    },
+    Pass {
+        player: i32,
+        board: u64,
+        is_end_of_cycle: bool,
+    },
+}
+
+pub struct SrvGameState {
+    pub prev_action: u64,
+    pub last_action: u64,
+    pub action: SrvAction,
+    pub board: u64,
+    pub board_score: u64,
+    pub has_passed: u8,
+    pub turn: i32,
+    pub round: u8,
+    pub rounds: u8,
+    pub cards: [u64; 4],
+    pub played_cards: u64,
+    pub score: [i16; 4],
+    pub delta_score: [i16; 4],
+    pub card_cnt: [u8; 4],
+    pub rules: rules::RuleSet,
+    pub scoring: scoring::ScoringPolicy,
+    // Result of the last round, None until a round is over.
+    pub result: Option<scoring::RoundResult>,
+    pub phase: Phase,
+    // Tiebreaks of the standings, see SrvGameState::standings.
+    pub rounds_won: [u8; 4],
+    pub cards_left: [u16; 4],
+    // This round, how often each player chopped a 2 and how often they had a 2 chopped.
+    pub chops: [u8; 4],
+    pub chopped: [u8; 4],
+    // The lowest card dealt, it must be in the first lead of the match. Two players may not
+    // get the lowest card of the deck.
+    opening_card: u64,
+    // The deals of the match come from this seed, see SrvGameState::set_seed.
+    pub seed: u64,
+    rng: StdRng,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    // Waiting for the players to be ready for the first round.
+    WaitingForReady,
+    // The cards are dealt, waiting for the opening lead.
+    Dealing,
+    Playing,
+    // Waiting for the players to be ready for the next round.
+    RoundOver,
+    MatchOver,
+}
+
+// A player's place in the match, see SrvGameState::standings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Standing {
+    pub player: usize,
+    // 1 is the lead, players that are still tied share the place.
+    pub place: usize,
+    pub score: i16,
+    pub rounds_won: u8,
+    pub cards_left: u16,
+}
+
+pub mod scoring {
+    use super::*;
+
+    // Which plays fall under the assist rule. The player that played before the winner's
+    // last play, while holding cards that beat it, pays the penalties of all players.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Assist {
+        Off,
+        Singles,
+        // Singles, pairs, sets and five-card hands.
+        AllPlays,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct ScoringPolicy {
+        // Penalty for the number of cards left in a hand, 0 to 18 cards. Only three players
+        // get more than 13 cards.
+        pub card_points: [i16; 19],
+        // The penalty doubles for every 2 left in the hand.
+        pub double_for_twos: bool,
+        // Points the player whose 2 is chopped pays to the player that chopped it. A chop is
+        // quads or a straight flush on a board with a 2, see RuleSet::bombs.
+        pub chop_bonus: i16,
+        pub assist: Assist,
+    }
+
+    impl ScoringPolicy {
+        // 1 point per card, double for 10 cards or more and triple for 13 cards or more.
+        pub const STANDARD: ScoringPolicy = ScoringPolicy {
+            card_points: [
+                0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 20, 22, 24, 39, 42, 45, 48, 51, 54,
+            ],
+            double_for_twos: false,
+            chop_bonus: 0,
+            assist: Assist::Singles,
+        };
+
+        // 1 point per card, nothing else.
+        pub const FLAT: ScoringPolicy = ScoringPolicy {
+            card_points: [
+                0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18,
+            ],
+            double_for_twos: false,
+            chop_bonus: 0,
+            assist: Assist::Off,
+        };
+
+        // Score the round that the player on turn just won.
+        pub fn score_round(&self, gs: &SrvGameState) -> RoundResult {
+            let winner = gs.turn as usize;
+            let prev_player = gs.prev_action as usize & 0x3;
+            let hand = gs.last_action & cards::ALL;
+
+            let assist = match self.assist {
+                Assist::Off => false,
+                Assist::Singles => hand.count_ones() == 1,
+                Assist::AllPlays => true,
+            };
+            let assisted_by = if assist
+                && prev_player != winner
+                && !rules::legal_moves_with(hand, gs.cards[prev_player], &gs.rules).is_empty()
+            {
+                trace!(
+                    "Assist! PP{} {:16x} CP{} {:16x}",
+                    prev_player,
+                    gs.cards[prev_player],
+                    winner,
+                    hand,
+                );
+                Some(prev_player)
+            } else {
+                None
+            };
+
+            let mut penalty: [i16; 4] = [0; 4];
+            for (i, p) in penalty.iter_mut().enumerate() {
+                if i != winner {
+                    *p = self.card_penalty(gs, i);
+                }
+            }
+            let total: i16 = penalty.iter().sum();
+
+            let mut delta_score: [i16; 4] = [0; 4];
+            match assisted_by {
+                // The assisting player pays for everyone.
+                Some(p) => delta_score[p] = -total,
+                None => {
+                    for (d, p) in delta_score.iter_mut().zip(penalty.iter()) {
+                        *d = -p;
+                    }
+                }
+            }
+            delta_score[winner] = total;
+            for (p, d) in delta_score.iter_mut().enumerate() {
+                *d += (gs.chops[p] as i16 - gs.chopped[p] as i16) * self.chop_bonus;
+            }
+
+            RoundResult {
+                winner,
+                cards_left: gs.card_cnt,
+                penalty,
+                assisted_by,
+                chops: gs.chops,
+                chopped: gs.chopped,
+                instant_win: None,
+                delta_score,
+            }
+        }
+
+        // What the cards left in the hand of `player` cost.
+        fn card_penalty(&self, gs: &SrvGameState, player: usize) -> i16 {
+            let twos = cards::Hand::ALL.of_rank(cards::Rank::TWO).mask();
+            let penalty = self.card_points[gs.card_cnt[player] as usize];
+            if self.double_for_twos {
+                return penalty << (gs.cards[player] & twos).count_ones();
+            }
+            penalty
+        }
+
+        // Score a round that `winner` won with the deal, everyone else pays for a full hand.
+        pub fn score_instant_win(
+            &self,
+            gs: &SrvGameState,
+            winner: usize,
+            win: rules::InstantWin,
+        ) -> RoundResult {
+            let mut penalty: [i16; 4] = [0; 4];
+            for (i, p) in penalty.iter_mut().enumerate() {
+                if i != winner {
+                    *p = self.card_penalty(gs, i);
+                }
+            }
+            let total: i16 = penalty.iter().sum();
+
+            let mut delta_score: [i16; 4] = [0; 4];
+            for (d, p) in delta_score.iter_mut().zip(penalty.iter()) {
+                *d = -p;
+            }
+            delta_score[winner] = total;
+
+            RoundResult {
+                winner,
+                cards_left: gs.card_cnt,
+                penalty,
+                assisted_by: None,
+                chops: [0; 4],
+                chopped: [0; 4],
+                instant_win: Some(win),
+                delta_score,
+            }
+        }
+    }
+
+    impl Default for ScoringPolicy {
+        fn default() -> Self {
+            ScoringPolicy::STANDARD
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct RoundResult {
+        pub winner: usize,
+        pub cards_left: [u8; 4],
+        // What each player owes for the cards left, before the assist rule.
+        pub penalty: [i16; 4],
+        pub assisted_by: Option<usize>,
+        // How often each player chopped a 2 and had a 2 chopped, see SrvGameState::chops.
+        pub chops: [u8; 4],
+        pub chopped: [u8; 4],
+        // The winner didn't play, the deal won the round.
+        pub instant_win: Option<rules::InstantWin>,
+        // Score change of each player, they add up to 0.
+        pub delta_score: [i16; 4],
+    }
+}
+
+pub mod bot {
+    use super::*;
+    use rand::seq::SliceRandom;
+    use std::fmt;
+    use std::str::FromStr;
+
+    // What a player knows when it is their turn.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct View {
+        pub rules: rules::RuleSet,
+        pub player: usize,
+        pub hand: u64,
+        pub board: u64,
+        // Cards left of every seat, 0 for the seats not in the game.
+        pub card_cnt: [u8; 4],
+        // A bit for every player that passed this cycle.
+        pub has_passed: u8,
+        // The cards played this round.
+        pub played_cards: u64,
+        // The first lead of the match must contain this card, 0 otherwise.
+        pub opening_card: u64,
+    }
+
+    impl View {
+        // A view with only your hand and the board.
+        pub fn new(rules: rules::RuleSet, hand: u64, board: u64) -> View {
+            View {
+                rules,
+                player: 0,
+                hand,
+                board,
+                card_cnt: [0; 4],
+                has_passed: 0,
+                played_cards: 0,
+                opening_card: 0,
+            }
+        }
+
+        // Every combo you can play, lowest score first.
+        pub fn legal_moves(&self) -> Vec<cards::Combo> {
+            let mut moves = rules::legal_moves_with(self.board, self.hand, &self.rules);
+            if self.board == 0 && self.hand & self.opening_card != 0 {
+                moves.retain(|c| c.hand().mask() & self.opening_card != 0);
+            }
+            moves
+        }
+
+        // The fewest cards one of the other players has left.
+        pub fn min_opponent_cards(&self) -> u8 {
+            let players = self.rules.players as usize;
+            (0..players)
+                .filter(|&p| p != self.player && self.card_cnt[p] != 0)
+                .map(|p| self.card_cnt[p])
+                .min()
+                .unwrap_or(cards::ALL.count_ones() as u8)
+        }
+    }
+
+    // A computer player. Send, so the server thread can own its bots.
+    pub trait Bot: Send {
+        // Returns the cards to play, None is pass.
+        fn play(&mut self, view: &View) -> Option<u64>;
+    }
+
+    // How a computer player picks a move.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Strategy {
+        // Beat a single card with the lowest card possible, pass on anything else.
+        Lowest,
+        // Lead with the lowest card, always pass when there is a board.
+        Passive,
+        // Any legal move or a pass, picked at random.
+        Random,
+        // Always play the lowest combo that beats the board, lead with as many cards as
+        // possible.
+        Greedy,
+        // Like greedy, but keeps the twos and five-card combos for later and blocks a player
+        // that is almost out.
+        Heuristic,
+        // Searches the moves with ISMCTS, see ismcts::Ismcts.
+        Ismcts,
+    }
+
+    impl Strategy {
+        pub const ALL: [Strategy; 6] = [
+            Strategy::Lowest,
+            Strategy::Passive,
+            Strategy::Random,
+            Strategy::Greedy,
+            Strategy::Heuristic,
+            Strategy::Ismcts,
+        ];
+
+        pub fn name(self) -> &'static str {
+            match self {
+                Strategy::Lowest => "lowest",
+                Strategy::Passive => "passive",
+                Strategy::Random => "random",
+                Strategy::Greedy => "greedy",
+                Strategy::Heuristic => "heuristic",
+                Strategy::Ismcts => "ismcts",
+            }
+        }
+    }
+
+    impl Strategy {
+        // A bot that plays this strategy, the strategies that pick at random use the seed.
+        pub fn bot(self, seed: u64) -> Box<dyn Bot> {
+            match self {
+                Strategy::Random => Box::new(RandomLegal::new(seed)),
+                Strategy::Ismcts => {
+                    let budget = ismcts::Budget::Iterations(ismcts::ITERATIONS);
+                    Box::new(ismcts::Ismcts::new(budget, seed))
+                }
+                _ => Box::new(Fixed(self)),
+            }
+        }
+    }
+
+    // The strategies that don't pick at random, they need no state.
+    struct Fixed(Strategy);
+
+    impl Bot for Fixed {
+        fn play(&mut self, view: &View) -> Option<u64> {
+            match self.0 {
+                Strategy::Greedy => greedy(view),
+                Strategy::Heuristic => heuristic(view),
+                strategy => simple(strategy, view),
+            }
+        }
+    }
+
+    impl FromStr for Strategy {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            Strategy::ALL
+                .iter()
+                .find(|strategy| strategy.name() == s)
+                .copied()
+                .ok_or_else(|| format!("Unknown bot strategy {}", s))
+        }
+    }
+
+    impl fmt::Display for Strategy {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{}", self.name())
+        }
+    }
+
+    fn simple(strategy: Strategy, view: &View) -> Option<u64> {
+        let rules = &view.rules;
+        if view.board == 0 {
+            return Some(rules.higher_single_card(0, view.hand));
+        }
+        match strategy {
+            Strategy::Lowest if view.board.count_ones() == 1 => {
+                let card = rules.higher_single_card(view.board, view.hand);
+                if card == 0 {
+                    None
+                } else {
+                    Some(card)
+                }
+            }
+            _ => None,
+        }
+    }
+
+    // Any legal move or a pass.
+    pub struct RandomLegal {
+        rng: StdRng,
+    }
+
+    impl RandomLegal {
+        pub fn new(seed: u64) -> RandomLegal {
+            RandomLegal {
+                rng: StdRng::seed_from_u64(seed),
+            }
+        }
+    }
+
+    impl Bot for RandomLegal {
+        fn play(&mut self, view: &View) -> Option<u64> {
+            let mut moves: Vec<Option<u64>> = view
+                .legal_moves()
+                .iter()
+                .map(|c| Some(c.hand().mask()))
+                .collect();
+            // The lead can't be passed.
+            if view.board != 0 || moves.is_empty() {
+                moves.push(None);
+            }
+            *moves.choose(&mut self.rng).unwrap()
+        }
+    }
+
+    fn greedy(view: &View) -> Option<u64> {
+        let moves = view.legal_moves();
+        if view.board != 0 {
+            return moves.first().map(|c| c.hand().mask());
+        }
+        // Get rid of the lowest card together with as many others as possible.
+        let lowest = view.rules.higher_single_card(0, view.hand);
+        moves
+            .iter()
+            .rev()
+            .filter(|c| c.hand().mask() & lowest != 0)
+            .max_by_key(|c| c.card_count())
+            .map(|c| c.hand().mask())
+    }
+
+    // The five-card combos of the hand that don't share cards, the highest picked first.
+    fn five_card_combos(view: &View) -> u64 {
+        let mut fives: Vec<cards::Combo> = rules::legal_moves_with(0, view.hand, &view.rules)
+            .into_iter()
+            .filter(|c| c.card_count() == 5)
+            .collect();
+        fives.reverse();
+        fives.iter().fold(0, |kept, c| {
+            let cards = c.hand().mask();
+            if kept & cards == 0 {
+                kept | cards
+            } else {
+                kept
+            }
+        })
+    }
+
+    fn heuristic(view: &View) -> Option<u64> {
+        let moves = view.legal_moves();
+        let hand = view.hand;
+
+        // Going out wins the round.
+        if let Some(c) = moves.iter().find(|c| c.hand().mask() == hand) {
+            return Some(c.hand().mask());
+        }
+
+        let twos = cards::Hand::from_mask(hand)
+            .of_rank(cards::Rank::TWO)
+            .mask();
+        let fives = five_card_combos(view);
+        // Twos are for the end of the round.
+        let keep_twos = (hand & !twos).count_ones() > 3;
+        let keeps = |c: &&cards::Combo| {
+            let cards = c.hand().mask();
+            if keep_twos && cards & twos != 0 {
+                return false;
+            }
+            // Play a five-card combo as a whole or leave it.
+            c.card_count() == 5 || cards & fives == 0
+        };
+
+        // Someone is almost out, play the highest combo to keep the board.
+        if view.min_opponent_cards() <= 2 {
+            return moves.last().map(|c| c.hand().mask());
+        }
+
+        if view.board != 0 {
+            return moves.iter().find(keeps).map(|c| c.hand().mask());
+        }
+
+        // Lead with the lowest card, or with a five-card combo that has it.
+        let lowest = view.rules.higher_single_card(0, hand);
+        let lead = moves
+            .iter()
+            .rev()
+            .filter(keeps)
+            .filter(|c| c.hand().mask() & lowest != 0)
+            .max_by_key(|c| c.card_count())
+            .or_else(|| moves.iter().find(keeps))
+            .or_else(|| moves.first());
+        lead.map(|c| c.hand().mask())
+    }
+}
+
+// Information set Monte Carlo tree search. Every iteration deals the cards you can't see
+// at random to the other players and plays the round out with the rules engine. The moves
+// are a tree shared by all these deals.
+pub mod ismcts {
+    use super::*;
+    use bot::{Bot, View};
+    use rand::seq::SliceRandom;
+    use std::time::{Duration, Instant};
+
+    // Iterations of Strategy::Ismcts.
+    pub const ITERATIONS: u32 = 1000;
+    // How much the search tries moves that don't look good yet.
+    const EXPLORATION: f64 = 0.7;
+
+    // When to stop searching and play the best move.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Budget {
+        Iterations(u32),
+        Time(Duration),
+    }
+
+    pub struct Ismcts {
+        budget: Budget,
+        rng: StdRng,
+    }
+
+    struct Node {
+        // The move to this node, 0 is a pass.
+        cards: u64,
+        // The player that made the move.
+        player: usize,
+        visits: u32,
+        // The number of times the move could be made.
+        available: u32,
+        reward: f64,
+        children: Vec<usize>,
+    }
+
+    impl Node {
+        fn new(cards: u64, player: usize) -> Node {
+            Node {
+                cards,
+                player,
+                visits: 0,
+                available: 0,
+                reward: 0.0,
+                children: Vec::new(),
+            }
+        }
+
+        // An unvisited move goes first, it has no reward to divide yet.
+        fn ucb(&self) -> f64 {
+            if self.visits == 0 {
+                return f64::INFINITY;
+            }
+            let visits = self.visits as f64;
+            self.reward / visits + EXPLORATION * ((self.available as f64).ln() / visits).sqrt()
+        }
+    }
+
+    impl Ismcts {
+        pub fn new(budget: Budget, seed: u64) -> Ismcts {
+            Ismcts {
+                budget,
+                rng: StdRng::seed_from_u64(seed),
+            }
+        }
+
+        // Deals the cards you can't see to the other players, as many as they have left.
+        fn determinize(&mut self, view: &View) -> SrvGameState {
+            let mut unseen: Vec<cards::Card> =
+                cards::Hand::from_mask(cards::ALL & !view.hand & !view.played_cards)
+                    .iter()
+                    .collect();
+            unseen.shuffle(&mut self.rng);
+
+            let mut gs = SrvGameState::with_rules(1, view.rules);
+            let mut unseen = unseen.into_iter();
+            for p in 0..view.rules.players as usize {
+                gs.cards[p] = if p == view.player {
+                    view.hand
+                } else {
+                    (&mut unseen)
+                        .take(view.card_cnt[p] as usize)
+                        .fold(0, |hand, card| hand | card.mask())
+                };
+                gs.card_cnt[p] = gs.cards[p].count_ones() as u8;
+            }
+            gs.round = 1;
+            gs.opening_card = view.opening_card;
+            gs.board = view.board;
+            gs.board_score = rules::score_hand_with(view.board, &view.rules);
+            gs.has_passed = view.has_passed;
+            gs.played_cards = view.played_cards;
+            gs.turn = view.player as i32;
+            gs.phase = if view.played_cards == 0 {
+                Phase::Dealing
+            } else {
+                Phase::Playing
+            };
+            gs
+        }
+
+        fn search(&mut self, view: &View) -> u64 {
+            let mut tree = vec![Node::new(0, view.player)];
+            let start = Instant::now();
+            let mut iterations = 0;
+
+            loop {
+                match self.budget {
+                    Budget::Iterations(max) if iterations >= max => break,
+                    Budget::Time(max) if start.elapsed() >= max => break,
+                    _ => {}
+                }
+                iterations += 1;
+
+                let mut gs = self.determinize(view);
+                let mut path = vec![0];
+                let mut node = 0;
+
+                // Follow the tree, add one new move to it.
+                while gs.result.is_none() {
+                    let moves = moves(&gs);
+                    if moves.is_empty() {
+                        break;
+                    }
+                    let player = gs.turn as usize;
+                    let mut untried = moves.clone();
+                    for c in 0..tree[node].children.len() {
+                        let child = tree[node].children[c];
+                        if let Some(i) = untried.iter().position(|&m| m == tree[child].cards) {
+                            untried.swap_remove(i);
+                            tree[child].available += 1;
+                        }
+                    }
+
+                    let next = if let Some(&cards) = untried.choose(&mut self.rng) {
+                        let mut child = Node::new(cards, player);
+                        child.available = 1;
+                        tree.push(child);
+                        let child = tree.len() - 1;
+                        tree[node].children.push(child);
+                        child
+                    } else {
+                        let best = tree[node]
+                            .children
+                            .iter()
+                            .filter(|&&c| moves.contains(&tree[c].cards))
+                            .max_by(|&&a, &&b| tree[a].ucb().total_cmp(&tree[b].ucb()));
+                        *best.unwrap()
+                    };
+                    if !make_move(&mut gs, tree[next].cards) {
+                        break;
+                    }
+                    path.push(next);
+                    node = next;
+                    if tree[node].visits == 0 {
+                        break;
+                    }
+                }
+
+                // Play the round out at random.
+                while gs.result.is_none() {
+                    let moves = moves(&gs);
+                    match moves.choose(&mut self.rng) {
+                        Some(&cards) if make_move(&mut gs, cards) => {}
+                        _ => break,
+                    }
+                }
+
+                // A round that got stuck counts as a draw, so the new move is visited too.
+                let rewards = match gs.result {
+                    Some(result) => rewards(&result, view.rules.players as usize),
+                    None => [0.5; 4],
+                };
+                for &n in path.iter() {
+                    tree[n].visits += 1;
+                    tree[n].reward += rewards[tree[n].player];
+                }
+            }
+
+            let best = tree[0].children.iter().max_by_key(|&&c| tree[c].visits);
+            best.map_or(0, |&c| tree[c].cards)
+        }
+    }
+
+    impl Bot for Ismcts {
+        fn play(&mut self, view: &View) -> Option<u64> {
+            let moves = view.legal_moves();
+            let cards = match moves.len() {
+                0 => 0,
+                1 if view.board == 0 => moves[0].hand().mask(),
+                _ => self.search(view),
+            };
+            if cards == 0 {
+                None
+            } else {
+                Some(cards)
+            }
+        }
+    }
+
+    // The moves of the player in turn, 0 is a pass.
+    fn moves(gs: &SrvGameState) -> Vec<u64> {
+        let hand = gs.cards[gs.turn as usize];
+        let mut moves: Vec<u64> = rules::legal_moves_with(gs.board, hand, &gs.rules)
+            .iter()
+            .map(|c| c.hand().mask())
+            .filter(|&m| hand & gs.opening_card == 0 || m & gs.opening_card != 0)
+            .collect();
+        if gs.phase == Phase::Playing && gs.board != 0 {
+            moves.push(0);
+        }
+        moves
+    }
+
+    fn make_move(gs: &mut SrvGameState, cards: u64) -> bool {
+        let turn = gs.turn;
+        let ret = if cards == 0 {
+            gs.pass(turn)
+        } else {
+            gs.play(turn, cards)
+        };
+        ret.is_ok()
+    }
+
+    // The score of the round scaled from 0 for the biggest loss to 1 for the winner.
+    fn rewards(result: &scoring::RoundResult, players: usize) -> [f64; 4] {
+        let delta = &result.delta_score[..players];
+        let min = *delta.iter().min().unwrap() as f64;
+        let max = *delta.iter().max().unwrap() as f64;
+        let mut rewards = [0.0; 4];
+        for (p, &d) in delta.iter().enumerate() {
+            rewards[p] = if max > min {
+                (d as f64 - min) / (max - min)
+            } else {
+                0.5
+            };
+        }
+        rewards
+    }
+}
+
+// Splits a hand into the combos to play it out with, and picks the play that fits the split.
+pub mod hint {
+    use super::*;
+    use std::collections::HashMap;
+
+    // How good a split is, lower is better. First the plays that lose control of the board,
+    // then all plays.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+    pub struct Cost {
+        pub losing_plays: u8,
+        pub plays: u8,
+    }
+
+    pub struct Planner {
+        // The combos of the hand by the bit of their lowest card, true when nobody can beat
+        // the combo.
+        combos: Vec<Vec<(cards::Combo, bool)>>,
+        // The best split of a part of the hand: its cost and the combo with the lowest card.
+        best: HashMap<u64, (Cost, cards::Combo)>,
+    }
+
+    impl Planner {
+        // `unseen` are the cards the other players may have.
+        pub fn new(hand: u64, unseen: u64, rules: &rules::RuleSet) -> Planner {
+            let mut combos = vec![Vec::new(); 64];
+            for combo in rules::legal_moves_with(0, hand, rules) {
+                let cards = combo.hand().mask();
+                // Nobody has five cards that beat quads or a straight flush, most of the time.
+                let control = match combo.kind() {
+                    cards::ComboKind::Quads | cards::ComboKind::StraightFlush => true,
+                    _ if combo.card_count() == 5 => false,
+                    _ => rules::legal_moves_with(cards, unseen, rules).is_empty(),
+                };
+                combos[cards.trailing_zeros() as usize].push((combo, control));
+            }
+            Planner {
+                combos,
+                best: HashMap::new(),
+            }
+        }
+
+        // The cost of the best split of the cards, a part of the hand.
+        pub fn cost(&mut self, cards: u64) -> Cost {
+            if cards == 0 {
+                return Cost::default();
+            }
+            if let Some(&(cost, _)) = self.best.get(&cards) {
+                return cost;
+            }
+
+            // The lowest card has to go in one of its combos.
+            let low = cards.trailing_zeros() as usize;
+            let mut best: Option<(Cost, cards::Combo)> = None;
+            for i in 0..self.combos[low].len() {
+                let (combo, control) = self.combos[low][i];
+                let mask = combo.hand().mask();
+                if mask & !cards != 0 {
+                    continue;
+                }
+                let rest = self.cost(cards ^ mask);
+                let cost = Cost {
+                    losing_plays: rest.losing_plays + !control as u8,
+                    plays: rest.plays + 1,
+                };
+                match best {
+                    Some((b, _)) if b <= cost => {}
+                    _ => best = Some((cost, combo)),
                 }
-                return cards::Kind::STRAIGHT | straigth_score;
             }
+            // Every card is a single, there is always a split.
+            let best = best.unwrap();
+            self.best.insert(cards, best);
+            best.0
         }
 
-        if !is_straight && is_flush {
-            return cards::Kind::FLUSH | highest_card;
+        // The best split of the cards, a part of the hand.
+        pub fn split(&mut self, cards: u64) -> Vec<cards::Combo> {
+            let mut split = Vec::new();
+            let mut rest = cards;
+            while rest != 0 {
+                self.cost(rest);
+                let combo = self.best[&rest].1;
+                split.push(combo);
+                rest ^= combo.hand().mask();
+            }
+            split
         }
-
-        return 0;
     }
-}
 
-pub struct GameState {
-    pub sm: network::StateMessage,
-    pub srn: std::io::Stdout,
-    pub board: u64,
-    pub board_score: u64,
-    pub cards_selected: u64,
-    pub auto_pass: bool,
-    pub i_am_ready: bool,
-    pub is_valid_hand: bool,
-    pub hand_score: u64,
-}
+    // The best split of the hand, `unseen` are the cards the other players may have.
+    pub fn partition(hand: u64, unseen: u64, rules: &rules::RuleSet) -> Vec<cards::Combo> {
+        Planner::new(hand, unseen, rules).split(hand)
+    }
 
-pub struct SrvGameState {
-    pub prev_action: u64,
-    pub last_action: u64,
-    pub board_score: u64,
-    pub has_passed: u8,
-    pub turn: i32,
-    pub round: u8,
-    pub rounds: u8,
-    pub cards: [u64; 4],
-    pub played_cards: u64,
-    pub score: [i16; 4],
-    pub card_cnt: [u8; 4],
+    // The play that leaves the best split of the rest of the hand, the lowest cards first.
+    // None is pass, when no play makes the rest of the hand better.
+    pub fn hint(view: &bot::View) -> Option<u64> {
+        let hand = view.hand;
+        let unseen = cards::ALL & !hand & !view.played_cards;
+        let mut planner = Planner::new(hand, unseen, &view.rules);
+        let now = planner.cost(hand);
+
+        let best = view
+            .legal_moves()
+            .into_iter()
+            .map(|c| (planner.cost(hand ^ c.hand().mask()), c.hand().highest(), c))
+            .min();
+        match best {
+            Some((cost, _, c)) if view.board == 0 || cost < now => Some(c.hand().mask()),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -380,13 +2188,87 @@ pub enum SrvGameError {
     PlayerPlayedIllegalCard(u64),
     InvalidHand,
     AllreadyPassed,
+    // The hand must have the same number of cards as the board.
+    WrongCardCount(u32),
+    // The hand doesn't beat the board.
+    HandTooLow,
+    // The first lead of the match must contain the lowest card, the 3♦ in the standard rules.
+    MissingOpeningCard,
+    // A five-card hand can only be beaten by the same kind in these rules.
+    WrongKind,
+    // The action isn't allowed in this phase of the match.
+    WrongPhase(Phase),
+    // The leader of a trick has to play, there is no board to pass on.
+    PassOnEmptyBoard,
+}
+
+impl std::fmt::Display for SrvGameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SrvGameError::NotPlayersTurn => write!(f, "Not your turn"),
+            SrvGameError::PlayerPlayedIllegalCard(c) => write!(f, "Cards not in hand {:16x}", c),
+            SrvGameError::InvalidHand => write!(f, "Not a valid hand"),
+            SrvGameError::AllreadyPassed => write!(f, "Already passed"),
+            SrvGameError::WrongCardCount(n) => write!(f, "Play {} card(s)", n),
+            SrvGameError::HandTooLow => write!(f, "Doesn't beat the board"),
+            SrvGameError::MissingOpeningCard => write!(f, "The first play needs the lowest card"),
+            SrvGameError::WrongKind => write!(f, "Play the same kind as the board"),
+            SrvGameError::WrongPhase(p) => write!(f, "Not allowed while {:?}", p),
+            SrvGameError::PassOnEmptyBoard => write!(f, "The leader can't pass"),
+        }
+    }
+}
+
+impl GameState {
+    // What you see of the game, for auto-play.
+    pub fn view(&self) -> bot::View {
+        let sm = &self.sm;
+        let mut view = bot::View::new(self.rules, self.hand, self.board);
+        view.player = sm.your_index as usize;
+        view.played_cards = self.played_cards;
+        for (p, player) in sm.players.iter().enumerate() {
+            view.card_cnt[p] = player.num_cards as u8;
+            if player.has_passed_this_cycle {
+                view.has_passed |= 1 << p;
+            }
+        }
+        // You lead the match, so you have the lowest card dealt.
+        if sm.round == 1 && self.played_cards == 0 {
+            view.opening_card = self.rules.higher_single_card(0, self.hand);
+        }
+        view
+    }
+
+    // Call for every new StateMessage.
+    pub fn track_played_cards(&mut self) {
+        match self.sm.action.action_type {
+            network::StateMessageActionType::DEAL => self.played_cards = 0,
+            network::StateMessageActionType::PLAY => {
+                self.played_cards |= self.sm.action.cards.into_card().unwrap_or(0);
+            }
+            _ => {}
+        }
+    }
+
+    // The cards that you haven't seen, in the hands of the other players. With two players
+    // the undealt cards are in it too.
+    pub fn unseen_cards(&self) -> u64 {
+        cards::ALL & !self.hand & !self.played_cards
+    }
 }
 
 impl SrvGameState {
     pub fn new(rounds: u8) -> Self {
+        SrvGameState::with_rules(rounds, rules::RuleSet::default())
+    }
+
+    pub fn with_rules(rounds: u8, rules: rules::RuleSet) -> Self {
+        let seed = rand::random();
         SrvGameState {
             prev_action: 0,
             last_action: 0,
+            action: SrvAction::Update,
+            board: 0,
             board_score: 0,
             has_passed: 0,
             turn: -1,
@@ -395,44 +2277,118 @@ impl SrvGameState {
             cards: [0; 4],
             played_cards: 0,
             score: [0; 4],
-            card_cnt: [13; 4],
+            delta_score: [0; 4],
+            card_cnt: [0; 4],
+            rules,
+            scoring: scoring::ScoringPolicy::default(),
+            result: None,
+            phase: Phase::WaitingForReady,
+            rounds_won: [0; 4],
+            cards_left: [0; 4],
+            chops: [0; 4],
+            chopped: [0; 4],
+            opening_card: rules.lowest_card().mask(),
+            seed,
+            rng: StdRng::seed_from_u64(seed),
         }
     }
-    pub fn deal(&mut self, cards: Option<&[u64]>) {
+    // Deal the match again from the start of `seed`, to replay a game.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+    pub fn deal(&mut self, cards: Option<&[u64]>) -> Result<(), SrvGameError> {
+        if !matches!(self.phase, Phase::WaitingForReady | Phase::RoundOver) {
+            return Err(SrvGameError::WrongPhase(self.phase));
+        }
+
         // create cards
         if let Some(cards) = cards {
             assert_eq!(cards.len(), 4);
             self.cards.copy_from_slice(cards);
         } else {
-            self.cards = deck::deal();
+            self.cards = deck::deal_with(&mut self.rng, &self.rules);
         }
 
         // Setup
         self.round += 1;
         self.has_passed = 0;
+        self.action = SrvAction::Deal;
+        self.board = 0;
         self.board_score = 0;
         self.has_passed = 0;
-        self.card_cnt = [13; 4];
+        self.played_cards = 0;
+        self.chops = [0; 4];
+        self.chopped = [0; 4];
+        // A full hand for every seat, with three players one of them got the last card too.
+        let hand_size = self.rules.hand_size() as u8;
+        for (p, cnt) in self.card_cnt.iter_mut().enumerate() {
+            *cnt = if p < self.rules.players as usize {
+                hand_size.max(self.cards[p].count_ones() as u8)
+            } else {
+                0
+            };
+        }
+        self.result = None;
+        self.phase = Phase::Dealing;
 
         let mut m: u64 = 0;
         for c in self.cards.iter() {
             m |= c;
-            println!("C 0x{:16x} count {}", c, c.count_ones());
+            trace!("C 0x{:16x} count {}", c, c.count_ones());
         }
-        let im = !(m | 0xFFF);
-        println!("! 0x{:16x} M 0x{:16x} count {}", im, m, im.count_ones());
+        let im = !m & cards::ALL;
+        trace!("! 0x{:16x} M 0x{:16x} count {}", im, m, im.count_ones());
         // assert!(m == 0xFFFF_FFFF_FFFF_F000);
 
         // Which player to start
         if self.round == 1 {
-            self.turn = self.cards.iter().position(|&x| x & 0x1000 != 0).unwrap() as i32;
+            let rules = &self.rules;
+            self.opening_card = cards::Hand::from_mask(m)
+                .iter()
+                .min_by_key(|&card| rules.card_value(card))
+                .map_or(0, cards::Card::mask);
+            self.turn = self
+                .cards
+                .iter()
+                .position(|&x| x & self.opening_card != 0)
+                .unwrap() as i32;
         } else {
             let p = (self.last_action & 0x3) as i32;
-            println!("Last action {:16x} P{}", self.last_action, p);
+            trace!("Last action {:16x} P{}", self.last_action, p);
             self.turn = p;
         }
+
+        if self.rules.instant_wins {
+            if let Some((p, win)) = self.instant_win() {
+                trace!("Instant win P{} {}", p, win);
+                let result = self.scoring.score_instant_win(self, p, win);
+                // The winner leads the next round.
+                self.last_action = p as u64;
+                self.end_round(result);
+                self.action = SrvAction::InstantWin {
+                    player: p as i32,
+                    win,
+                };
+            }
+        }
+        Ok(())
+    }
+
+    // The player with the highest instant win in the dealt hands. A tie goes to the first
+    // player in turn from the player that leads.
+    fn instant_win(&self) -> Option<(usize, rules::InstantWin)> {
+        let lead = self.turn as usize;
+        let players = self.rules.players as usize;
+        (0..players)
+            .map(|i| (lead + i) % players)
+            .filter_map(|p| rules::InstantWin::of_hand(self.cards[p]).map(|win| (p, win)))
+            .min_by_key(|&(_, win)| win)
     }
     pub fn play(&mut self, player: i32, hand: u64) -> Result<(), SrvGameError> {
+        if !matches!(self.phase, Phase::Dealing | Phase::Playing) {
+            return Err(SrvGameError::WrongPhase(self.phase));
+        }
         if player != self.turn {
             return Err(SrvGameError::NotPlayersTurn);
         }
@@ -445,46 +2401,98 @@ impl SrvGameState {
             return Err(SrvGameError::PlayerPlayedIllegalCard(illegal_cards));
         }
 
-        let score = rules::score_hand(hand);
+        let score = rules::score_hand_with(hand, &self.rules);
         if score == 0 {
             return Err(SrvGameError::InvalidHand);
         }
 
-        if self.board_score != 0 && score <= self.board_score {
-            return Err(SrvGameError::InvalidHand);
+        let opening_card = self.opening_card;
+        if self.round == 1 && pc & opening_card != 0 && hand & opening_card == 0 {
+            return Err(SrvGameError::MissingOpeningCard);
+        }
+
+        let combo = cards::Combo::with_rules(cards::Hand::from_mask(hand), &self.rules).unwrap();
+        let board_combo = cards::Combo::with_rules(cards::Hand::from_mask(self.board), &self.rules);
+        let is_bomb = matches!(board_combo, Some(b) if self.rules.is_bomb_on(combo, b));
+
+        if self.board_score != 0 && !is_bomb {
+            let board_cnt = self.board.count_ones();
+            if hand.count_ones() != board_cnt {
+                return Err(SrvGameError::WrongCardCount(board_cnt));
+            }
+            let same_kind = score & cards::Kind::TYPE == self.board_score & cards::Kind::TYPE;
+            if board_cnt == 5 && !same_kind && !self.rules.mixed_five_card_kinds {
+                return Err(SrvGameError::WrongKind);
+            }
+            if score <= self.board_score {
+                return Err(SrvGameError::HandTooLow);
+            }
+        }
+
+        // A chop: quads or a straight flush beat a single 2 or a pair of 2s.
+        let twos = cards::Hand::ALL.of_rank(cards::Rank::TWO).mask();
+        if self.board & twos != 0 && self.board.count_ones() <= 2 && combo.is_bomb() {
+            self.chops[p] += 1;
+            self.chopped[self.last_action as usize & 0x3] += 1;
         }
 
         self.prev_action = self.last_action;
         self.last_action = hand | (p as u64) | ((self.last_action & 0x3) << 2);
 
+        let board = self.board;
+        self.board = hand;
         self.board_score = score;
         self.cards[p] ^= hand;
+        self.played_cards |= hand;
 
         let cnt = hand.count_ones();
         self.card_cnt[p] -= cnt as u8;
 
         if self.card_cnt[p] == 0 {
-            self.calc_score();
-            println!("No more cards! Score: {:?}", self.score);
-            self.turn = -1;
+            let result = self.scoring.score_round(self);
+            self.end_round(result);
+            trace!("No more cards! Score: {:?}", self.score);
+            self.action = SrvAction::Play {
+                player,
+                hand,
+                board,
+                is_end_of_cycle: false,
+            };
 
             return Ok(());
         }
 
+        self.phase = Phase::Playing;
+
         // if pc.count_ones() == 0 {
-        //     println!("No more cards!");
+        //     trace!("No more cards!");
         //     self.turn = -1;
         //     return Ok(());
         // }
 
         self.next_player();
 
+        // Playing the highest card(s) clears the board and keeps the turn.
+        self.action = SrvAction::Play {
+            player,
+            hand,
+            board,
+            is_end_of_cycle: self.board == 0,
+        };
+
         Ok(())
     }
     pub fn pass(&mut self, player: i32) -> Result<(), SrvGameError> {
+        // The opening lead can't be passed.
+        if self.phase != Phase::Playing {
+            return Err(SrvGameError::WrongPhase(self.phase));
+        }
         if player != self.turn {
             return Err(SrvGameError::NotPlayersTurn);
         }
+        if self.board == 0 {
+            return Err(SrvGameError::PassOnEmptyBoard);
+        }
         let b = 1 << player;
         if b & self.has_passed != 0 {
             return Err(SrvGameError::AllreadyPassed);
@@ -492,29 +2500,39 @@ impl SrvGameState {
 
         self.has_passed |= b;
 
+        let board = self.board;
         self.next_player();
 
+        self.action = SrvAction::Pass {
+            player,
+            board,
+            is_end_of_cycle: self.board == 0,
+        };
+
         Ok(())
     }
 
     fn next_player(&mut self) {
         let mut next = self.turn;
 
-        if self.board_score == 0x23f || self.board_score == 0x13f || self.board_score == 0x33f {
-            println!(
+        let combo = cards::Combo::with_rules(cards::Hand::from_mask(self.board), &self.rules);
+        if matches!(combo, Some(c) if self.rules.is_highest(c)) {
+            trace!(
                 "Play 2s which is the highest card bs {:3x}",
                 self.board_score
             );
+            self.board = 0;
             self.board_score = 0;
             self.has_passed = 0;
             return;
         }
 
-        for _ in 0..3 {
-            next = (next + 1) & 0x3;
+        let players = self.rules.players as i32;
+        for _ in 1..players {
+            next = (next + 1) % players;
 
             let b = 1 << next;
-            println!(
+            trace!(
                 "    TURN {} NEXT {} HP {:x} B {:x} SKIP {:x}",
                 self.turn,
                 next,
@@ -527,54 +2545,103 @@ impl SrvGameState {
                 break;
             }
         }
-        if self.has_passed.count_ones() == 3 {
+        if self.has_passed.count_ones() as i32 == players - 1 {
             // everyone has passed.
+            self.board = 0;
             self.board_score = 0;
             self.has_passed = 0;
-            println!("\tEveryone has passed bs {:3x}", self.board_score);
+            trace!("\tEveryone has passed bs {:3x}", self.board_score);
         }
 
         self.turn = next;
         return;
     }
-    fn calc_score(&mut self) {
-        let mut t: i16 = 0;
+    fn end_round(&mut self, result: scoring::RoundResult) {
+        for i in 0..4 {
+            self.score[i] += result.delta_score[i];
+            self.delta_score[i] = result.delta_score[i];
+            self.cards_left[i] += result.cards_left[i] as u16;
+        }
+        self.rounds_won[result.winner] += 1;
+        self.result = Some(result);
+        self.turn = -1;
+        self.phase = if self.round == self.rounds {
+            Phase::MatchOver
+        } else {
+            Phase::RoundOver
+        };
+    }
 
-        let prev_player = self.prev_action as usize & 0x3;
-        let curr_player = self.last_action as usize & 0x3;
-        let hand = self.last_action & 0xFFFF_FFFF_FFFF_F000;
+    // What the player sees of the game, for a bot.
+    pub fn view(&self, player: usize) -> bot::View {
+        let first_lead = self.round == 1 && self.played_cards == 0;
+        bot::View {
+            rules: self.rules,
+            player,
+            hand: self.cards[player],
+            board: self.board,
+            card_cnt: self.card_cnt,
+            has_passed: self.has_passed,
+            played_cards: self.played_cards,
+            opening_card: if first_lead { self.opening_card } else { 0 },
+        }
+    }
 
-        // Assist!
-        let assisted = self.board_score & 0xF00 == 0x100
-            && prev_player != curr_player
-            && hand < self.cards[prev_player];
+    pub fn is_match_over(&self) -> bool {
+        self.phase == Phase::MatchOver
+    }
 
-        if assisted {
-            println!(
-                "Assist! PP{} {:16x} CP{} {:16x}",
-                prev_player, self.cards[prev_player], self.turn, hand,
-            )
+    // The players from first to last place. Ties on the score are broken by the most rounds
+    // won and then by the fewest cards left over all rounds.
+    pub fn standings(&self) -> Vec<Standing> {
+        use std::cmp::Reverse;
+
+        let mut standings: Vec<Standing> = (0..self.rules.players as usize)
+            .map(|player| Standing {
+                player,
+                place: 1,
+                score: self.score[player],
+                rounds_won: self.rounds_won[player],
+                cards_left: self.cards_left[player],
+            })
+            .collect();
+        let key = |s: &Standing| (Reverse(s.score), Reverse(s.rounds_won), s.cards_left);
+        standings.sort_by_key(key);
+        for i in 1..standings.len() {
+            standings[i].place = if key(&standings[i]) == key(&standings[i - 1]) {
+                standings[i - 1].place
+            } else {
+                i + 1
+            };
         }
+        standings
+    }
 
-        let mut delta_score: [i16; 4] = [0; 4];
-        for i in 0..4 {
-            let mut s = self.card_cnt[i] as i16;
-            if s == 13 {
-                s *= 3
-            } else if s > 9 {
-                s *= 2
-            };
-            t += s;
-            delta_score[i] = s;
+    // The winner of the match, None while the match is on or when the first place is shared.
+    pub fn winner(&self) -> Option<usize> {
+        if !self.is_match_over() {
+            return None;
         }
-        if assisted {
-            self.score[prev_player] -= t
-        } else {
-            for i in 0..4 {
-                self.score[i] -= delta_score[i];
-            }
+        let standings = self.standings();
+        if standings[1].place == 1 {
+            return None;
         }
-        self.score[self.turn as usize] += t;
+        Some(standings[0].player)
+    }
+
+    // Start over with the same rules and number of rounds once the match is over.
+    pub fn new_match(&mut self) -> Result<(), SrvGameError> {
+        if !matches!(self.phase, Phase::WaitingForReady | Phase::MatchOver) {
+            return Err(SrvGameError::WrongPhase(self.phase));
+        }
+        // The seed of the next match comes from this one, so a seeded server replays all
+        // its matches.
+        let seed = self.rng.gen();
+        let scoring = self.scoring;
+        *self = SrvGameState::with_rules(self.rounds, self.rules);
+        self.scoring = scoring;
+        self.set_seed(seed);
+        Ok(())
     }
 }
 
@@ -582,6 +2649,280 @@ impl SrvGameState {
 mod tests {
     use super::*;
 
+    // The mask of a hand like "3d 4c 2s".
+    fn mask(s: &str) -> u64 {
+        s.parse::<cards::Hand>().unwrap().mask()
+    }
+
+    #[test]
+    fn bot_strategy() {
+        use bot::{Strategy, View};
+
+        let hand: u64 = 0x1000 | 0x2_0000 | 0x8000_0000_0000_0000;
+        let rules = rules::RuleSet::default();
+        let view = |board| View::new(rules, hand, board);
+        assert_eq!("lowest".parse(), Ok(Strategy::Lowest));
+        assert!("smart".parse::<Strategy>().is_err());
+        for strategy in Strategy::ALL.iter() {
+            assert_eq!(strategy.to_string().parse().as_ref(), Ok(strategy));
+        }
+
+        // Lead with the lowest card.
+        assert_eq!(Strategy::Lowest.bot(0).play(&view(0)), Some(0x1000));
+        assert_eq!(Strategy::Passive.bot(0).play(&view(0)), Some(0x1000));
+        // Beat a single card.
+        assert_eq!(Strategy::Lowest.bot(0).play(&view(0x4000)), Some(0x2_0000));
+        assert_eq!(Strategy::Passive.bot(0).play(&view(0x4000)), None);
+        assert_eq!(
+            Strategy::Lowest.bot(0).play(&view(0x4000_0000_0000_0000)),
+            Some(0x8000_0000_0000_0000)
+        );
+        // Pass on a pair or when nothing is higher.
+        assert_eq!(Strategy::Lowest.bot(0).play(&view(0x3000)), None);
+        assert_eq!(
+            Strategy::Lowest
+                .bot(0)
+                .play(&View::new(rules, 0x1000, 0x8000_0000_0000_0000)),
+            None
+        );
+    }
+
+    #[test]
+    fn bot_strategies_test() {
+        use bot::{Strategy, View};
+
+        let rules = rules::RuleSet::default();
+        let hand = mask("3d 3c 5h 6s 7d 8c 9h Ts Td Qc Kh 2s 2d");
+        let view = |board| View::new(rules, hand, board);
+
+        // Random plays a legal move or passes.
+        let mut random = Strategy::Random.bot(1);
+        for _ in 0..20 {
+            if let Some(cards) = random.play(&view(mask("4d"))) {
+                assert!(rules.can_play(mask("4d"), cards));
+            }
+            let cards = random.play(&view(0)).unwrap();
+            assert!(rules.can_play(0, cards));
+        }
+
+        // Greedy leads the lowest card with as many others as it can.
+        assert_eq!(Strategy::Greedy.bot(0).play(&view(0)), Some(mask("3d 3c")));
+        assert_eq!(
+            Strategy::Greedy.bot(0).play(&view(mask("4d"))),
+            Some(mask("5h"))
+        );
+        assert_eq!(
+            Strategy::Greedy.bot(0).play(&view(mask("6d"))),
+            Some(mask("6s"))
+        );
+        assert_eq!(
+            Strategy::Greedy.bot(0).play(&view(mask("Ad"))),
+            Some(mask("2d"))
+        );
+        assert_eq!(
+            Strategy::Greedy.bot(0).play(&view(mask("8d 8h"))),
+            Some(mask("Td Ts"))
+        );
+
+        // The heuristic leaves the straight and the twos alone.
+        assert_eq!(
+            Strategy::Heuristic.bot(0).play(&view(0)),
+            Some(mask("3d 3c"))
+        );
+        assert_eq!(
+            Strategy::Heuristic.bot(0).play(&view(mask("4d"))),
+            Some(mask("5h"))
+        );
+        assert_eq!(
+            Strategy::Heuristic.bot(0).play(&view(mask("6d"))),
+            Some(mask("Td"))
+        );
+        assert_eq!(Strategy::Heuristic.bot(0).play(&view(mask("Ad"))), None);
+        assert_eq!(Strategy::Heuristic.bot(0).play(&view(mask("8d 8h"))), None);
+        // But blocks a player that is almost out.
+        let mut almost_out = view(mask("Ad"));
+        almost_out.player = 1;
+        almost_out.card_cnt = [1, 13, 9, 6];
+        assert_eq!(
+            Strategy::Heuristic.bot(0).play(&almost_out),
+            Some(mask("2s"))
+        );
+        // And goes out when it can.
+        let view = View::new(rules, mask("5d 2s"), mask("Ad"));
+        assert_eq!(Strategy::Heuristic.bot(0).play(&view), Some(mask("2s")));
+        let view = View::new(rules, mask("5d 5s"), mask("4d 4s"));
+        assert_eq!(Strategy::Heuristic.bot(0).play(&view), Some(mask("5d 5s")));
+
+        // The first lead of the match has the opening card.
+        let mut first_lead = View::new(rules, mask("3d 3c 3h 4d 5d 6d 7d 8d 9d 9c"), 0);
+        first_lead.opening_card = mask("3d");
+        for strategy in Strategy::ALL.iter() {
+            let mut bot = strategy.bot(1);
+            let cards = bot.play(&first_lead).unwrap();
+            assert!(cards & mask("3d") != 0, "{}", strategy);
+        }
+
+        // The server shows the bots what the player sees.
+        let mut gs = SrvGameState::new(1);
+        gs.set_seed(9);
+        gs.deal(None).unwrap();
+        let turn = gs.turn as usize;
+        let view = gs.view(turn);
+        assert_eq!(view.hand, gs.cards[turn]);
+        assert!(view.opening_card & view.hand != 0);
+        let cards = Strategy::Heuristic.bot(0).play(&view).unwrap();
+        gs.play(turn as i32, cards).unwrap();
+        let view = gs.view(gs.turn as usize);
+        assert_eq!(view.played_cards, cards);
+        assert_eq!(view.board, cards);
+        assert_eq!(view.opening_card, 0);
+        assert_eq!(view.card_cnt[turn] as u32, 13 - cards.count_ones());
+    }
+
+    #[test]
+    fn ismcts_test() {
+        use bot::{Bot, View};
+        use cards::Hand;
+        use ismcts::{Budget, Ismcts};
+        use std::time::Duration;
+
+        let rules = rules::RuleSet::default();
+
+        // Going out wins.
+        let mut view = View::new(rules, mask("5d 5s"), mask("4d 4s"));
+        view.card_cnt = [2, 13, 13, 13];
+        view.played_cards = mask("4d 4s");
+        let mut bot = Ismcts::new(Budget::Iterations(100), 1);
+        assert_eq!(bot.play(&view), Some(mask("5d 5s")));
+
+        // The next player has one card left, lead the 2♠ first and then the 3♦.
+        let hand = mask("3d 2s");
+        let mut view = View::new(rules, hand, 0);
+        view.card_cnt = [2, 1, 12, 12];
+        view.played_cards = Hand::from_mask(cards::ALL & !hand)
+            .iter()
+            .take(25)
+            .fold(0, |m, c| m | c.mask());
+        let mut bot = Ismcts::new(Budget::Iterations(200), 2);
+        assert_eq!(bot.play(&view), Some(mask("2s")));
+
+        // The same seed gives the same move.
+        let mut gs = SrvGameState::new(1);
+        gs.set_seed(4);
+        gs.deal(None).unwrap();
+        let view = gs.view(gs.turn as usize);
+        let a = Ismcts::new(Budget::Iterations(50), 3).play(&view);
+        let b = Ismcts::new(Budget::Iterations(50), 3).play(&view);
+        assert_eq!(a, b);
+        assert!(gs.play(gs.turn, a.unwrap()).is_ok());
+
+        // Or search for a while.
+        let view = gs.view(gs.turn as usize);
+        let budget = Budget::Time(Duration::from_millis(20));
+        match Ismcts::new(budget, 5).play(&view) {
+            Some(cards) => assert!(gs.play(gs.turn, cards).is_ok()),
+            None => assert!(gs.pass(gs.turn).is_ok()),
+        }
+    }
+
+    #[test]
+    fn hint_test() {
+        use bot::View;
+        use cards::ComboKind;
+
+        let rules = rules::RuleSet::default();
+
+        // A flush, a full house, a pair and a single.
+        let hand = mask("3d 5d 8d Td Kd 4c 4h 4s 7c 7h 9s 9h Jc");
+        let split = hint::partition(hand, cards::ALL & !hand, &rules);
+        let mut kinds: Vec<ComboKind> = split.iter().map(|c| c.kind()).collect();
+        kinds.sort();
+        assert_eq!(
+            kinds,
+            [
+                ComboKind::One,
+                ComboKind::Pair,
+                ComboKind::Flush,
+                ComboKind::FullHouse
+            ]
+        );
+        assert_eq!(split.iter().fold(0, |m, c| m | c.hand().mask()), hand);
+
+        // Nothing beats the 2♠, so it doesn't count as losing control.
+        let mut planner = hint::Planner::new(mask("3d 2s"), cards::ALL & !mask("3d 2s"), &rules);
+        assert_eq!(planner.cost(mask("2s")).losing_plays, 0);
+        assert_eq!(planner.cost(mask("3d 2s")).losing_plays, 1);
+        assert_eq!(planner.cost(mask("3d 2s")).plays, 2);
+
+        // Lead the weakest combo of the split.
+        let hand = mask("3d 3c 6h 7s 8d 9c Th Kd 2s");
+        let view = |board| View::new(rules, hand, board);
+        assert_eq!(hint::hint(&view(0)), Some(mask("3d 3c")));
+        // Play the combo that fits, don't break the straight or the pair.
+        assert_eq!(hint::hint(&view(mask("Jd"))), Some(mask("Kd")));
+        assert_eq!(hint::hint(&view(mask("4d 4s"))), None);
+        assert_eq!(
+            hint::hint(&view(mask("3h 4h 5h 6d 7d"))),
+            Some(mask("6h 7s 8d 9c Th"))
+        );
+        assert_eq!(hint::hint(&view(mask("8h"))), Some(mask("Kd")));
+        // The first lead has the opening card.
+        let mut first_lead = View::new(rules, mask("3d 4c 4h 5s 5h"), 0);
+        first_lead.opening_card = mask("3d");
+        assert!(hint::hint(&first_lead).unwrap() & mask("3d") != 0);
+    }
+
+    #[test]
+    fn card_tracking_test() {
+        let mut srv = SrvGameState::new(2);
+        srv.set_seed(11);
+        srv.deal(None).unwrap();
+
+        let mut gs = GameState {
+            sm: network::StateMessage::new(None),
+            srn: std::io::stdout(),
+            board: 0,
+            board_score: 0,
+            cards_selected: 0,
+            auto_pass: false,
+            i_am_ready: false,
+            is_valid_hand: false,
+            hand_score: 0,
+            turn_deadline: None,
+            rules: rules::RuleSet::default(),
+            hand: 0,
+            played_cards: 0x1000,
+        };
+        let update = |srv: &SrvGameState, gs: &mut GameState| {
+            gs.sm = network::StateMessage::from_srv_game_state(srv, 0);
+            gs.hand = gs.sm.your_hand.to_card();
+            gs.track_played_cards();
+        };
+
+        // A new deal, nothing is played yet.
+        update(&srv, &mut gs);
+        assert_eq!(gs.played_cards, 0);
+        assert_eq!(gs.unseen_cards(), cards::ALL & !srv.cards[0]);
+
+        // Every play adds its cards, a pass doesn't.
+        let mut played = 0;
+        for _ in 0..8 {
+            let turn = srv.turn;
+            let view = srv.view(turn as usize);
+            match bot::Strategy::Greedy.bot(0).play(&view) {
+                Some(cards) => {
+                    srv.play(turn, cards).unwrap();
+                    played |= cards;
+                }
+                None => srv.pass(turn).unwrap(),
+            }
+            update(&srv, &mut gs);
+            assert_eq!(gs.played_cards, played);
+        }
+        assert_eq!(gs.played_cards, srv.played_cards);
+        assert_eq!(gs.unseen_cards(), cards::ALL & !srv.cards[0] & !played);
+    }
+
     #[test]
     fn a_rules_sizes() {
         assert!(rules::is_valid_hand(0) == false);
@@ -735,11 +3076,12 @@ mod tests {
     }
     #[test]
     fn c_deal_hand() {
+        let standard = rules::RuleSet::default();
         // No cards generated
-        assert!(deck::deal() != [0, 0, 0, 0]);
+        assert!(deck::deal_with(&mut rand::thread_rng(), &standard) != [0, 0, 0, 0]);
         // Detect shuffle is did not work at all.
         assert!(
-            deck::deal()
+            deck::deal_with(&mut rand::thread_rng(), &standard)
                 != [
                     0x1111_1111_1111_1000,
                     0x2222_2222_2222_2000,
@@ -747,6 +3089,30 @@ mod tests {
                     0x8888_8888_8888_8000
                 ]
         );
+        // A seed always deals the same hands.
+        let seeded = |seed| deck::deal_with(&mut StdRng::seed_from_u64(seed), &standard);
+        let hands = seeded(42);
+        assert_eq!(hands.iter().fold(0, |m, h| m | h), cards::ALL);
+        assert_eq!(seeded(42), hands);
+        assert!(seeded(43) != hands);
+
+        // And so does the game state, also for the next match.
+        let mut a = SrvGameState::new(1);
+        let mut b = SrvGameState::new(1);
+        a.set_seed(7);
+        b.set_seed(7);
+        a.deal(None).unwrap();
+        b.deal(None).unwrap();
+        assert_eq!(a.cards, b.cards);
+        assert_eq!(a.seed, 7);
+        a.phase = Phase::MatchOver;
+        b.phase = Phase::MatchOver;
+        a.new_match().unwrap();
+        b.new_match().unwrap();
+        assert_eq!(a.seed, b.seed);
+        a.deal(None).unwrap();
+        b.deal(None).unwrap();
+        assert_eq!(a.cards, b.cards);
     }
     #[test]
     fn d_cards_test() {
@@ -765,6 +3131,85 @@ mod tests {
         assert!(cards::has_suit(card) == cards::Kind::SPADES);
     }
 
+    #[test]
+    fn e_card_hand_types() {
+        use cards::{Card, Combo, ComboKind, Hand, Kind, Rank};
+        use std::convert::TryFrom;
+
+        let three = Card::new(Rank::THREE, Kind::DIAMONDS).unwrap();
+        assert_eq!(three, Card::THREE_OF_DIAMONDS);
+        assert_eq!(three.mask(), 0x1000);
+        assert_eq!(
+            Card::from_mask(0x8000_0000_0000_0000),
+            Some(Card::TWO_OF_SPADES)
+        );
+        assert_eq!(Card::from_mask(0x3000), None);
+        assert_eq!(Card::new(Rank::TWO, 0b11), None);
+        assert!(Card::new(Rank::ACE, Kind::SPADES).unwrap() < Card::TWO_OF_SPADES);
+        assert_eq!(Card::new(Rank::TEN, Kind::CLUBS).unwrap().to_string(), "Tc");
+        assert_eq!("3d".parse(), Ok(Card::THREE_OF_DIAMONDS));
+        assert_eq!("2♠".parse(), Ok(Card::TWO_OF_SPADES));
+        assert_eq!("tC".parse::<Card>().unwrap().to_string(), "Tc");
+        assert!("1d".parse::<Card>().is_err());
+        assert!("3x".parse::<Card>().is_err());
+        assert!("10d".parse::<Card>().is_err());
+        assert!("".parse::<Card>().is_err());
+
+        assert!(Hand::try_from(0x1001).is_err());
+        let hand = Hand::try_from(0x8000_0000_0002_1000).unwrap();
+        assert_eq!(hand.len(), 3);
+        assert_eq!(hand.to_string(), "3d 4c 2s");
+        assert_eq!(hand.lowest(), Some(Card::THREE_OF_DIAMONDS));
+        assert_eq!(hand.highest(), Some(Card::TWO_OF_SPADES));
+        assert_eq!(hand.iter().nth(1).map(|c| c.rank()), Some(Rank::FOUR));
+        assert_eq!(hand.iter().collect::<Hand>(), hand);
+        assert_eq!("2s 3d  4c".parse(), Ok(hand));
+        assert_eq!(hand.to_string().parse(), Ok(hand));
+        assert_eq!("".parse(), Ok(Hand::EMPTY));
+        assert!("3d 3d".parse::<Hand>().is_err());
+        assert!("3d,4d".parse::<Hand>().is_err());
+        let parsed: Hand = "3d 4d 5h Ts As 2s".parse().unwrap();
+        assert_eq!(parsed.mask(), 0x8800_0800_0041_1000);
+        assert_eq!(parsed.to_string(), "3d 4d 5h Ts As 2s");
+
+        let mut rest = hand - Hand::from(Card::TWO_OF_SPADES);
+        assert_eq!(rest.mask(), 0x2_1000);
+        rest |= Hand::from(Card::TWO_OF_SPADES);
+        assert_eq!(rest, hand);
+        assert_eq!((hand & !hand), Hand::EMPTY);
+        assert_eq!(hand.of_rank(Rank::FOUR).mask(), 0x2_0000);
+        assert!(Hand::from_mask(0xF000).combo().is_none());
+
+        let pair = Combo::new(Hand::from_mask(0x3000)).unwrap();
+        assert_eq!(pair.kind(), ComboKind::Pair);
+        assert_eq!(pair.score(), rules::score_hand(0x3000));
+        assert_eq!(pair.to_string(), "Pair 3d 3c");
+        let single = Combo::new(Hand::from_mask(0x8000)).unwrap();
+        assert!(!single.beats(pair));
+        assert!(Combo::new(Hand::from_mask(0xC000)).unwrap().beats(pair));
+
+        let straight = Hand::from_mask(0x1000_0000_0111_1000).combo().unwrap();
+        assert_eq!(straight.kind(), ComboKind::StraightFlush);
+
+        // Nothing beats the 2♠ or three 2s.
+        assert!(Hand::from(Card::TWO_OF_SPADES)
+            .combo()
+            .unwrap()
+            .is_highest());
+        assert!(Hand::from_mask(0xE000_0000_0000_0000)
+            .combo()
+            .unwrap()
+            .is_highest());
+        assert!(!Hand::from_mask(0x3000_0000_0000_0000)
+            .combo()
+            .unwrap()
+            .is_highest());
+        assert!(!Hand::from_mask(0x4000_0000_0000_0000)
+            .combo()
+            .unwrap()
+            .is_highest());
+    }
+
     #[test]
     fn assist_test() {
         let mut gs = SrvGameState::new(1);
@@ -773,7 +3218,8 @@ mod tests {
             0x2222_2222_2222_2000,
             0x4444_4444_4444_4000,
             0x8888_8888_8888_8000,
-        ]));
+        ]))
+        .unwrap();
         assert_eq!(gs.turn, 0);
 
         // reduct cards
@@ -798,14 +3244,15 @@ mod tests {
             0x2222_2222_2222_2000,
             0x4444_4444_4444_4000,
             0x8888_8888_8888_8000,
-        ]));
+        ]))
+        .unwrap();
         assert_eq!(gs.turn, 0);
 
         // reduct cards
         gs.cards = [0x5000, 0x8000, 0x2000, 0x40000];
         gs.card_cnt = [2, 1, 1, 1];
 
-        match gs.play(0, 0x4000) {
+        match gs.play(0, 0x1000) {
             Ok(_) => assert!(true),
             Err(_) => assert!(false),
         }
@@ -823,7 +3270,8 @@ mod tests {
             0x2222_2222_2222_2000,
             0x8444_4444_4444_4000,
             0x4888_8888_8888_8000,
-        ]));
+        ]))
+        .unwrap();
         assert_eq!(gs.turn, 0);
 
         // reduct cards
@@ -843,6 +3291,543 @@ mod tests {
             Err(_) => assert!(false),
         }
         assert_eq!(gs.score, [-24, -24, 87, -39]);
+        assert_eq!(gs.delta_score, [-24, -24, 87, -39]);
+    }
+
+    #[test]
+    fn play_rules_test() {
+        let mut gs = SrvGameState::new(2);
+        gs.deal(Some(&[
+            0x1111_1111_1111_1000,
+            0x2222_2222_2222_2000,
+            0x4444_4444_4444_4000,
+            0x8888_8888_8888_8000,
+        ]))
+        .unwrap();
+        assert_eq!(gs.turn, 0);
+
+        // The first lead must contain the 3♦.
+        assert!(matches!(
+            gs.play(0, 0x10000),
+            Err(SrvGameError::MissingOpeningCard)
+        ));
+        gs.play(0, 0x1000).unwrap();
+
+        // A five-card hand can't be played on a single.
+        assert!(matches!(
+            gs.play(1, 0x0002_2222_0000),
+            Err(SrvGameError::WrongCardCount(1))
+        ));
+        let e = gs.play(1, 0x0002_2222_0000).unwrap_err();
+        assert_eq!(e.to_string(), "Play 1 card(s)");
+        assert!(matches!(gs.play(1, 0x2000), Ok(())));
+        assert!(matches!(gs.play(2, 0x0040_0000_0000_0000), Ok(())));
+        assert!(matches!(gs.play(3, 0x8000), Err(SrvGameError::HandTooLow)));
+        assert!(matches!(gs.play(3, 0x0080_0000_0000_0000), Ok(())));
+
+        // Everyone passes, the leader of the next trick can't.
+        gs.pass(0).unwrap();
+        gs.pass(1).unwrap();
+        gs.pass(2).unwrap();
+        assert_eq!((gs.turn, gs.board), (3, 0));
+        let e = gs.pass(3).unwrap_err();
+        assert!(matches!(e, SrvGameError::PassOnEmptyBoard));
+        assert_eq!(e.to_string(), "The leader can't pass");
+    }
+
+    #[test]
+    fn rule_set_test() {
+        use rules::{RuleSet, SuitOrder, WrapStraights};
+
+        for (name, rules) in RuleSet::PRESETS.iter() {
+            assert_eq!(name.parse::<RuleSet>().as_ref(), Ok(rules));
+            assert_eq!(RuleSet::from_bits(rules.to_bits()).as_ref(), Some(rules));
+            assert_eq!(&rules.to_string(), name);
+        }
+        assert!("house".parse::<RuleSet>().is_err());
+        assert_eq!(RuleSet::from_bits(0x3), None);
+        let custom = RuleSet {
+            flush_by_suit: true,
+            wrap_straights: WrapStraights::Lowest,
+            ..RuleSet::STANDARD
+        };
+        assert_eq!(custom.name(), "custom");
+        let instant_win = RuleSet::preset("instant-win").unwrap();
+        assert!(instant_win.check().is_ok());
+        assert!(RuleSet {
+            players: 2,
+            ..instant_win
+        }
+        .check()
+        .is_ok());
+        assert!(RuleSet {
+            players: 3,
+            ..instant_win
+        }
+        .check()
+        .is_err());
+
+        // Suit order: 3♣ is lower than 3♦ and opens the match.
+        let clubs_low = RuleSet::preset("clubs-low").unwrap();
+        assert_eq!(clubs_low.suit_order, SuitOrder::ClubsDiamondsHeartsSpades);
+        assert_eq!(clubs_low.lowest_card().mask(), 0x2000);
+        assert!(
+            rules::score_hand_with(0x1000, &clubs_low) > rules::score_hand_with(0x2000, &clubs_low)
+        );
+        assert_eq!(
+            clubs_low.higher_single_card(0x1000, 0x2000 | 0x4000),
+            0x4000
+        );
+        let spades_low = RuleSet::preset("spades-low").unwrap();
+        assert_eq!(spades_low.lowest_card().mask(), 0x8000);
+        // The 2♥ is the highest card.
+        let score = rules::score_hand_with(0x4000_0000_0000_0000, &spades_low);
+        assert_eq!(score, cards::Kind::ONE | cards::Kind::HIGHEST);
+
+        // Wrapping straights: 23456 and A2345.
+        // A♦ 2♦ 3♦ 4♦ 5♣
+        let a2345: u64 = 0x0100_0000_0000_0000 | 0x1000_0000_0000_0000 | 0x1_1000 | 0x20_0000;
+        let s34567: u64 = 0x1000 | 0x1_0000 | 0x10_0000 | 0x100_0000 | 0x2000_0000;
+        let standard = RuleSet::STANDARD;
+        let low_wrap = RuleSet::preset("low-wrap").unwrap();
+        let no_twos = RuleSet::preset("no-twos").unwrap();
+        assert!(
+            rules::score_hand_with(a2345, &standard) > rules::score_hand_with(s34567, &standard)
+        );
+        assert!(
+            rules::score_hand_with(a2345, &low_wrap) < rules::score_hand_with(s34567, &low_wrap)
+        );
+        assert_eq!(
+            rules::score_hand_with(a2345, &low_wrap) & cards::Kind::TYPE,
+            cards::Kind::STRAIGHT
+        );
+        assert_eq!(rules::score_hand_with(a2345, &no_twos), 0);
+        // J-Q-K-A-2
+        let jqka2: u64 = 0x1000_0000_0000 | 0x1_0000_0000_0000 | 0x10_0000_0000_0000;
+        let jqka2 = jqka2 | 0x0100_0000_0000_0000 | 0x8000_0000_0000_0000;
+        assert_eq!(
+            rules::score_hand_with(jqka2, &standard) & cards::Kind::TYPE,
+            cards::Kind::STRAIGHT
+        );
+        assert_eq!(rules::score_hand_with(jqka2, &no_twos), 0);
+
+        // Flush by suit: a low heart flush beats a high club flush.
+        let suit_flush = RuleSet::preset("suit-flush").unwrap();
+        let hearts: u64 = 0x4000 | 0x40_0000 | 0x400_0000 | 0x4000_0000 | 0x4_0000_0000;
+        let clubs: u64 = 0x2_0000_0000_0000 | 0x20_0000_0000_0000 | 0x200_0000_0000_0000;
+        let clubs = clubs | 0x2000_0000_0000_0000 | 0x20_0000_0000;
+        assert!(
+            rules::score_hand_with(hearts, &standard) < rules::score_hand_with(clubs, &standard)
+        );
+        assert!(
+            rules::score_hand_with(hearts, &suit_flush)
+                > rules::score_hand_with(clubs, &suit_flush)
+        );
+
+        // Same kind: a full house can't be played on a straight.
+        let same_kind = RuleSet::preset("same-kind").unwrap();
+        let full_house: u64 = 0x7_0000_0000 | 0x3000_0000_0000;
+        assert!(standard.can_play(s34567, full_house));
+        assert!(!same_kind.can_play(s34567, full_house));
+        assert!(rules::legal_moves_with(s34567, full_house, &same_kind).is_empty());
+        assert!(same_kind.can_play(0, full_house));
+
+        let mut gs = SrvGameState::with_rules(1, same_kind);
+        gs.deal(Some(&[
+            0x1111_1111_1111_1000,
+            0x2222_2222_2222_2000,
+            0x4444_4444_4444_4000,
+            0x8888_8888_8888_8000,
+        ]))
+        .unwrap();
+        gs.cards[0] |= s34567;
+        gs.cards[1] = full_house;
+        gs.play(0, s34567).unwrap();
+        assert!(matches!(
+            gs.play(1, full_house),
+            Err(SrvGameError::WrongKind)
+        ));
+
+        // Bombs: quads or a straight flush beat a single or a pair, not a set.
+        let bombs = RuleSet::preset("bombs").unwrap();
+        let quads: u64 = 0xF_0000 | 0x10_0000;
+        let two_of_spades: u64 = 0x8000_0000_0000_0000;
+        assert!(bombs.can_play(two_of_spades, quads));
+        assert!(bombs.can_play(0xC000_0000_0000_0000, quads));
+        assert!(!bombs.can_play(0x7000_0000_0000_0000, quads));
+        assert!(!bombs.can_play(quads, two_of_spades));
+        assert!(!standard.can_play(two_of_spades, quads));
+        assert_eq!(
+            rules::legal_moves_with(two_of_spades, quads | 0x1000, &bombs).len(),
+            2
+        );
+        assert!(rules::legal_moves_with(two_of_spades, quads, &standard).is_empty());
+        let highest = cards::Combo::with_rules(cards::Hand::from_mask(two_of_spades), &bombs);
+        assert!(standard.is_highest(highest.unwrap()));
+        assert!(!bombs.is_highest(highest.unwrap()));
+
+        // The 2♠ doesn't clear the board, the next player can bomb it.
+        let mut gs = SrvGameState::with_rules(1, bombs);
+        gs.deal(Some(&[
+            0x1111_1111_1111_1000,
+            0x2222_2222_2222_2000,
+            0x4444_4444_4444_4000,
+            0x8888_8888_8888_8000,
+        ]))
+        .unwrap();
+        gs.cards[0] |= quads;
+        gs.play(0, 0x1000).unwrap();
+        gs.play(1, 0x2000_0000_0000_0000).unwrap();
+        gs.pass(2).unwrap();
+        gs.play(3, two_of_spades).unwrap();
+        assert_eq!(gs.turn, 0);
+        assert_eq!(gs.board, two_of_spades);
+        gs.play(0, quads).unwrap();
+        assert!(matches!(
+            gs.play(1, 0x2000_0000),
+            Err(SrvGameError::WrongCardCount(5))
+        ));
+
+        // The 3♣ opens with the clubs-low rules.
+        let mut gs = SrvGameState::with_rules(1, clubs_low);
+        gs.deal(Some(&[
+            0x1111_1111_1111_1000,
+            0x2222_2222_2222_2000,
+            0x4444_4444_4444_4000,
+            0x8888_8888_8888_8000,
+        ]))
+        .unwrap();
+        assert_eq!(gs.turn, 1);
+        assert!(matches!(
+            gs.play(1, 0x2_0000),
+            Err(SrvGameError::MissingOpeningCard)
+        ));
+        gs.play(1, 0x2000).unwrap();
+        assert!(matches!(gs.play(2, 0x4000), Ok(())));
+        assert!(matches!(gs.play(3, 0x8000), Ok(())));
+        assert!(matches!(gs.play(0, 0x1000), Err(SrvGameError::HandTooLow)));
+    }
+
+    #[test]
+    fn scoring_policy_test() {
+        let deal = [
+            0x1111_1111_1111_1000,
+            0x2222_2222_2222_2000,
+            0x4444_4444_4444_4000,
+            0x8888_8888_8888_8000,
+        ];
+
+        // The assist of the standard scoring, see assist_test.
+        let mut gs = SrvGameState::new(1);
+        gs.deal(Some(&deal)).unwrap();
+        gs.cards = [0x24000, 0x8000, 0x2000, 0x1000];
+        gs.card_cnt = [2, 1, 1, 1];
+        gs.play(0, 0x4000).unwrap();
+        gs.play(1, 0x8000).unwrap();
+        let result = gs.result.unwrap();
+        assert_eq!(result.winner, 1);
+        assert_eq!(result.assisted_by, Some(0));
+        assert_eq!(result.penalty, [1, 0, 1, 1]);
+        assert_eq!(result.delta_score, [-3, 3, 0, 0]);
+        assert_eq!(result.chopped, [0; 4]);
+
+        // Without the assist rule everyone pays for their own cards.
+        let mut gs = SrvGameState::new(1);
+        gs.scoring = scoring::ScoringPolicy::FLAT;
+        gs.deal(Some(&deal)).unwrap();
+        gs.cards = [0x24000, 0x8000, 0x2000, 0x1000];
+        gs.card_cnt = [2, 1, 1, 1];
+        gs.play(0, 0x4000).unwrap();
+        gs.play(1, 0x8000).unwrap();
+        assert_eq!(gs.result.unwrap().assisted_by, None);
+        assert_eq!(gs.delta_score, [-1, 3, -1, -1]);
+
+        // Assist on a pair, 4♦ 4♥ was left while 3♥ 3♠ went out.
+        let mut gs = SrvGameState::new(1);
+        gs.scoring.assist = scoring::Assist::AllPlays;
+        gs.deal(Some(&deal)).unwrap();
+        gs.cards = [0x53000, 0xC000, 0x2_0000, 0x8_0000];
+        gs.card_cnt = [4, 2, 1, 1];
+        gs.play(0, 0x3000).unwrap();
+        gs.play(1, 0xC000).unwrap();
+        assert_eq!(gs.result.unwrap().assisted_by, Some(0));
+        assert_eq!(gs.delta_score, [-4, 4, 0, 0]);
+
+        // Twos left in a hand double the penalty. Going out with a 2 is no chop.
+        let mut gs = SrvGameState::new(1);
+        gs.scoring.double_for_twos = true;
+        gs.scoring.chop_bonus = 5;
+        gs.deal(Some(&deal)).unwrap();
+        gs.cards = [
+            0x1000 | 0x1000_0000_0000_0000,
+            0x2000 | 0x20_0000,
+            0x8000_0000_0000_0000,
+            0x8000,
+        ];
+        gs.card_cnt = [2, 2, 1, 1];
+        gs.play(0, 0x1000).unwrap();
+        gs.play(1, 0x2000).unwrap();
+        gs.play(2, 0x8000_0000_0000_0000).unwrap();
+        let result = gs.result.unwrap();
+        assert_eq!(result.chopped, [0; 4]);
+        assert_eq!(result.assisted_by, None);
+        assert_eq!(result.penalty, [2, 1, 0, 1]);
+        assert_eq!(result.delta_score, [-2, -1, 4, -1]);
+        assert_eq!(gs.score, result.delta_score);
+
+        // Quads on the 2♣ chop it, the player of the 2 pays the chop bonus.
+        let mut gs = SrvGameState::with_rules(1, rules::RuleSet::preset("bombs").unwrap());
+        gs.scoring.chop_bonus = 5;
+        gs.deal(Some(&deal)).unwrap();
+        let quads = 0xF00_0000 | 0x4000_0000;
+        gs.cards = [
+            0x1000 | 0x1_0000,
+            0x2000_0000_0000_0000 | 0x20_0000,
+            quads,
+            0x8000,
+        ];
+        gs.card_cnt = [2, 2, 5, 1];
+        gs.play(0, 0x1000).unwrap();
+        gs.play(1, 0x2000_0000_0000_0000).unwrap();
+        gs.play(2, quads).unwrap();
+        let result = gs.result.unwrap();
+        assert_eq!(result.winner, 2);
+        assert_eq!(result.chops, [0, 0, 1, 0]);
+        assert_eq!(result.chopped, [0, 1, 0, 0]);
+        assert_eq!(result.penalty, [1, 1, 0, 1]);
+        assert_eq!(result.delta_score, [-1, -1 - 5, 3 + 5, -1]);
+        assert_eq!(gs.score, result.delta_score);
+
+        // Quads on a full house of 2s is no chop.
+        let mut gs = SrvGameState::with_rules(1, rules::RuleSet::preset("bombs").unwrap());
+        gs.deal(Some(&deal)).unwrap();
+        gs.cards = [
+            mask("3d 3c 3h 4d 4c 5d"),
+            mask("2d 2c 2h Kd Kc 6d"),
+            mask("9d 9c 9h 9s 7d 8d"),
+            mask("Jd"),
+        ];
+        gs.card_cnt = [6, 6, 6, 1];
+        gs.play(0, mask("3d 3c 3h 4d 4c")).unwrap();
+        gs.play(1, mask("2d 2c 2h Kd Kc")).unwrap();
+        gs.play(2, mask("9d 9c 9h 9s 7d")).unwrap();
+        assert_eq!(gs.chops, [0; 4]);
+        assert_eq!(gs.chopped, [0; 4]);
+    }
+
+    #[test]
+    fn instant_win_test() {
+        use rules::InstantWin;
+
+        let of_hand = |s: &str| InstantWin::of_hand(mask(s));
+        assert_eq!(
+            of_hand("3c 4d 5d 6d 7d 8d 9d Td Jd Qd Kd Ad 2s"),
+            Some(InstantWin::Dragon)
+        );
+        assert_eq!(
+            of_hand("3c 4d 5d 6d 7d 8d 9d Td Jd 2d 2c 2h 2s"),
+            Some(InstantWin::FourTwos)
+        );
+        assert_eq!(
+            of_hand("3c 3d 3h 5d 5c 5s 9d 9h 9s Jd Qd Kd Ad"),
+            Some(InstantWin::ThreeTriples)
+        );
+        assert_eq!(
+            of_hand("3c 3d 3h 3s 5d 5c 9d 9h Jd Jh Qd Qh Ad"),
+            Some(InstantWin::SixPairs)
+        );
+        assert_eq!(of_hand("3c 3d 4h 4s 5d 5c 9d 9h Jd Jh Qd Kh Ad"), None);
+        // Only a dealt hand of 13 cards.
+        assert_eq!(of_hand("3c 3d 4h 4s 5d 5c 9d 9h Jd Jh Qd Qh"), None);
+        for win in [InstantWin::Dragon, InstantWin::SixPairs].iter() {
+            assert_eq!(InstantWin::from_byte(win.to_byte()), Some(*win));
+        }
+        assert_eq!(InstantWin::from_byte(0), None);
+
+        // P1 has six pairs and P2 a dragon, the dragon wins.
+        let deal = [
+            mask("3h 4h 5h 6h 7h 8h 9h 9s Th Jh Qh Kh Ah"),
+            mask("3c 3s 4c 4s 5c 5s 6c 6s 7c 7s 8c 8s 9c"),
+            mask("3d 4d 5d 6d 7d 8d 9d Td Jd Qd Kd Ad 2d"),
+            mask("Tc Ts Jc Js Qc Qs Kc Ks Ac As 2c 2h 2s"),
+        ];
+        let mut gs = SrvGameState::new(2);
+        gs.deal(Some(&deal)).unwrap();
+        assert_eq!(gs.phase, Phase::Dealing);
+
+        let mut gs = SrvGameState::with_rules(2, rules::RuleSet::preset("instant-win").unwrap());
+        gs.deal(Some(&deal)).unwrap();
+        assert_eq!(gs.phase, Phase::RoundOver);
+        assert_eq!(gs.turn, -1);
+        assert_eq!(
+            gs.action,
+            SrvAction::InstantWin {
+                player: 2,
+                win: InstantWin::Dragon
+            }
+        );
+        let result = gs.result.unwrap();
+        assert_eq!(result.winner, 2);
+        assert_eq!(result.instant_win, Some(InstantWin::Dragon));
+        assert_eq!(gs.score, [-39, -39, 117, -39]);
+        assert_eq!(gs.rounds_won, [0, 0, 1, 0]);
+
+        // The winner leads the next round.
+        let deal = [
+            mask("3d 3c 4h 5s 6d 7c 8h 9s Td Jc Qh Ks 2d"),
+            mask("3h 3s 4d 5c 6h 7s 8d 9c Th Js Qd Kc 2h"),
+            mask("4c 4s 5d 5h 6c 7d 8c 9d Tc Jd Qc Kd 2c"),
+            mask("6s 7h 8s 9h Ts Jh Qs Kh Ad Ac Ah As 2s"),
+        ];
+        gs.deal(Some(&deal)).unwrap();
+        assert_eq!(gs.phase, Phase::Dealing);
+        assert_eq!(gs.turn, 2);
+    }
+
+    #[test]
+    fn player_count_test() {
+        use rand::SeedableRng;
+        use rules::RuleSet;
+
+        let three = RuleSet {
+            players: 3,
+            ..RuleSet::STANDARD
+        };
+        let two = RuleSet {
+            players: 2,
+            ..RuleSet::STANDARD
+        };
+        assert_eq!(RuleSet::from_bits(three.to_bits()), Some(three));
+        assert_eq!(RuleSet::from_bits(two.to_bits()), Some(two));
+        assert_eq!(three.name(), "standard");
+        assert_eq!(three.to_string(), "standard, 3 players");
+
+        // Three players get 17 cards, the one with the 3♦ gets the last card too.
+        let mut rng = StdRng::seed_from_u64(3);
+        let hands = deck::deal_with(&mut rng, &three);
+        assert_eq!(hands.iter().fold(0, |m, h| m | h), cards::ALL);
+        assert_eq!(hands[3], 0);
+        for hand in hands[..3].iter() {
+            let size = if hand & 0x1000 != 0 { 18 } else { 17 };
+            assert_eq!(hand.count_ones(), size);
+        }
+        // Two players get 13 cards, the rest is left out.
+        let hands = deck::deal_with(&mut rng, &two);
+        assert_eq!(hands[0].count_ones(), 13);
+        assert_eq!(hands[1].count_ones(), 13);
+        assert_eq!(hands[0] & hands[1], 0);
+        assert_eq!(hands[2] | hands[3], 0);
+
+        // Without the 3♦ the lowest dealt card opens.
+        let deal = [
+            mask("4d 5c 6h 7s 8d 9c Th Js Qd Kc Ah 2s 3h"),
+            mask("3c 4c 5h 6s 7d 8c 9h Ts Jd Qc Kh As 2d"),
+            0,
+            0,
+        ];
+        let mut gs = SrvGameState::with_rules(1, two);
+        gs.deal(Some(&deal)).unwrap();
+        assert_eq!(gs.turn, 1);
+        assert_eq!(gs.card_cnt, [13, 13, 0, 0]);
+        assert!(gs.play(1, 0x1000).is_err());
+        gs.play(1, 0x2000).unwrap();
+        assert_eq!(gs.turn, 0);
+        // One pass and the board is cleared.
+        gs.pass(0).unwrap();
+        assert_eq!(gs.turn, 1);
+        assert_eq!(gs.board, 0);
+        assert_eq!(gs.standings().len(), 2);
+
+        // The turn goes round the three seats.
+        let mut gs = SrvGameState::with_rules(1, three);
+        gs.set_seed(5);
+        gs.deal(None).unwrap();
+        let first = gs.turn;
+        assert!(gs.cards[first as usize] & 0x1000 != 0);
+        gs.play(first, 0x1000).unwrap();
+        assert_eq!(gs.turn, (first + 1) % 3);
+        gs.pass(gs.turn).unwrap();
+        assert_eq!(gs.turn, (first + 2) % 3);
+        gs.pass(gs.turn).unwrap();
+        assert_eq!(gs.turn, first);
+        assert_eq!(gs.board, 0);
+        assert_eq!(gs.standings().len(), 3);
+    }
+
+    #[test]
+    fn match_phases() {
+        let deal = [
+            0x1111_1111_1111_1000,
+            0x2222_2222_2222_2000,
+            0x4444_4444_4444_4000,
+            0x8888_8888_8888_8000,
+        ];
+        let mut gs = SrvGameState::new(2);
+        gs.scoring = scoring::ScoringPolicy::FLAT;
+        assert_eq!(gs.phase, Phase::WaitingForReady);
+        assert!(matches!(
+            gs.play(0, 0x1000),
+            Err(SrvGameError::WrongPhase(Phase::WaitingForReady))
+        ));
+
+        // Round 1, the opening lead can't be passed.
+        gs.deal(Some(&deal)).unwrap();
+        assert_eq!(gs.phase, Phase::Dealing);
+        assert!(matches!(
+            gs.pass(0),
+            Err(SrvGameError::WrongPhase(Phase::Dealing))
+        ));
+        gs.cards = [0x1000 | 0x1_0000, 0x2000, 0x4000, 0x8000];
+        gs.card_cnt = [2, 1, 1, 1];
+        gs.play(0, 0x1000).unwrap();
+        assert_eq!(gs.phase, Phase::Playing);
+        gs.pass(1).unwrap();
+        gs.pass(2).unwrap();
+        gs.pass(3).unwrap();
+        gs.play(0, 0x1_0000).unwrap();
+        assert_eq!(gs.phase, Phase::RoundOver);
+        assert!(matches!(
+            gs.pass(1),
+            Err(SrvGameError::WrongPhase(Phase::RoundOver))
+        ));
+        assert!(gs.new_match().is_err());
+        assert_eq!(gs.winner(), None);
+
+        // Round 2, P1 goes out and takes the lead.
+        gs.deal(Some(&deal)).unwrap();
+        assert_eq!(gs.turn, 0);
+        gs.cards = [0x1000 | 0x1_0000, 0x2000, 0x4000, 0x8000];
+        gs.card_cnt = [2, 1, 1, 1];
+        gs.play(0, 0x1000).unwrap();
+        gs.play(1, 0x2000).unwrap();
+        assert!(gs.is_match_over());
+        assert_eq!(gs.score, [2, 2, -2, -2]);
+        assert_eq!(gs.rounds_won, [1, 1, 0, 0]);
+        assert_eq!(gs.cards_left, [1, 1, 2, 2]);
+        assert!(matches!(
+            gs.deal(None),
+            Err(SrvGameError::WrongPhase(Phase::MatchOver))
+        ));
+
+        // Tied on all tiebreaks, the players share the place.
+        let standings = gs.standings();
+        let order: Vec<_> = standings.iter().map(|s| (s.player, s.place)).collect();
+        assert_eq!(order, [(0, 1), (1, 1), (2, 3), (3, 3)]);
+        assert_eq!(gs.winner(), None);
+
+        // A tie on the score goes to the most rounds won, then the fewest cards left.
+        gs.cards_left = [2, 1, 2, 2];
+        assert_eq!(gs.winner(), Some(1));
+        gs.rounds_won = [2, 1, 0, 0];
+        assert_eq!(gs.winner(), Some(0));
+        gs.score = [2, 3, -3, -2];
+        assert_eq!(gs.standings()[0].player, 1);
+        assert_eq!(gs.standings()[3].player, 2);
+
+        gs.new_match().unwrap();
+        assert_eq!(gs.phase, Phase::WaitingForReady);
+        assert_eq!((gs.round, gs.score), (0, [0; 4]));
+        assert_eq!(gs.scoring, scoring::ScoringPolicy::FLAT);
     }
 
     #[test]
@@ -871,4 +3856,55 @@ mod tests {
         let play = rules::higher_single_card(board, my_hand);
         assert_eq!(play, 0x8_0000_0000_0000);
     }
+
+    #[test]
+    fn legal_moves() {
+        // 3d 3c 3h 4d 5d 6d 7d 7s
+        let hand: u64 =
+            0x8000_0000 | 0x1000_0000 | 0x0100_0000 | 0x0010_0000 | 0x0001_0000 | 0x7000;
+
+        // A new lead: 8 singles, 3 + 1 pairs, 1 set and the five-card combos.
+        let moves = rules::legal_moves(0, hand);
+        let count = |n: usize| moves.iter().filter(|c| c.card_count() == n).count();
+        assert_eq!(count(1), 8);
+        assert_eq!(count(2), 4);
+        assert_eq!(count(3), 1);
+        assert!(moves
+            .iter()
+            .all(|c| rules::score_hand(c.hand().mask()) == c.score()));
+        assert!(moves.windows(2).all(|w| w[0].score() <= w[1].score()));
+        assert_eq!(moves[0].hand().mask(), 0x1000);
+
+        // Straight flush 3d-7d, straights 3-7 with 3c/3h/7s and the full house 333 77.
+        let five: Vec<u64> = moves
+            .iter()
+            .filter(|c| c.card_count() == 5)
+            .map(|c| c.hand().mask())
+            .collect();
+        assert!(five.contains(&0x1111_1000));
+        assert!(five.contains(&(0x7000 | 0x8000_0000 | 0x1000_0000)));
+        assert!(moves
+            .iter()
+            .any(|c| c.score() == cards::Kind::STRAIGHTFLUSH | 0x1c));
+
+        // Only the pair of 7s beats a pair of 6s.
+        let moves = rules::legal_moves(0x0600_0000, hand);
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].hand().mask(), 0x9000_0000);
+
+        // The full house and the straight flush beat a flush.
+        let flush: u64 = 0x2022_0020_2000;
+        assert_eq!(
+            rules::score_hand(flush) & cards::Kind::TYPE,
+            cards::Kind::FLUSH
+        );
+        let moves = rules::legal_moves(flush, hand);
+        assert_eq!(moves.len(), 2);
+        assert_eq!(moves[0].kind(), cards::ComboKind::FullHouse);
+        assert_eq!(moves[1].kind(), cards::ComboKind::StraightFlush);
+
+        // Nothing beats the 2s and an invalid board has no moves.
+        assert!(rules::legal_moves(0x8000_0000_0000_0000, hand).is_empty());
+        assert!(rules::legal_moves(0xF000, hand).is_empty());
+    }
 }
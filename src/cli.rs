@@ -46,6 +46,15 @@ pub mod display {
         TOGGLECARD11,
         TOGGLECARD12,
         TOGGLECARD13,
+        TOGGLECARD14,
+        TOGGLECARD15,
+        TOGGLECARD16,
+        TOGGLECARD17,
+        TOGGLECARD18,
+        UP,
+        DOWN,
+        NEWTABLE,
+        HINT,
     }
 
     // https://en.wikipedia.org/wiki/ANSI_escape_code
@@ -63,6 +72,10 @@ pub mod display {
     const COL_CLUBS: &str = "\u{1b}[32m";
     const COL_HEARTS: &str = "\u{1b}[31m";
     const COL_SPADES: &str = "\u{1b}[30m";
+    const COL_SEEN: &str = "\u{1b}[90m"; // Dark grey
+
+    // Columns right of the table for the card tracker.
+    const TRACKER_WIDTH: u16 = 30;
 
     pub fn clear(srn: &mut std::io::Stdout) -> Result<()> {
         execute!(srn, Clear(ClearType::All))
@@ -72,6 +85,29 @@ pub mod display {
         execute!(srn, SetTitle(&title))
     }
 
+    // Room for the biggest hand of the rules, 13 cards with four players.
+    fn hand_slots(rules: &big2rules::rules::RuleSet) -> usize {
+        if rules.players == 3 {
+            rules.hand_size() + 1
+        } else {
+            rules.hand_size()
+        }
+    }
+
+    // Columns the table is wider than the 80 columns of four players.
+    fn extra_width(rules: &big2rules::rules::RuleSet) -> u16 {
+        (hand_slots(rules) as u16 - 13) * 3
+    }
+
+    // Make room for the hands of the rules.
+    pub fn resize(srn: &mut std::io::Stdout, rules: &big2rules::rules::RuleSet) -> Result<()> {
+        execute!(
+            srn,
+            SetSize(80 + extra_width(rules) + TRACKER_WIDTH, 10),
+            Clear(ClearType::All)
+        )
+    }
+
     pub fn init(title: &str) -> Result<std::io::Stdout> {
         let mut srn = stdout();
 
@@ -79,7 +115,7 @@ pub mod display {
             srn,
             EnterAlternateScreen,
             EnableMouseCapture,
-            SetSize(80, 10),
+            SetSize(80 + TRACKER_WIDTH, 10),
             Clear(ClearType::All),
             //SetTitle(&title),
         )?;
@@ -168,6 +204,21 @@ pub mod display {
                 if x == 60 || x == 61 {
                     return UserEvent::TOGGLECARD13;
                 }
+                if x == 63 || x == 64 {
+                    return UserEvent::TOGGLECARD14;
+                }
+                if x == 66 || x == 67 {
+                    return UserEvent::TOGGLECARD15;
+                }
+                if x == 69 || x == 70 {
+                    return UserEvent::TOGGLECARD16;
+                }
+                if x == 72 || x == 73 {
+                    return UserEvent::TOGGLECARD17;
+                }
+                if x == 75 || x == 76 {
+                    return UserEvent::TOGGLECARD18;
+                }
             }
             if y == 1 {
                 if x >= 43 && x <= 49 {
@@ -209,7 +260,16 @@ pub mod display {
             KeyCode::Char('-') => return UserEvent::TOGGLECARD11,
             KeyCode::Char('=') => return UserEvent::TOGGLECARD12,
             KeyCode::Backspace => return UserEvent::TOGGLECARD13,
+            KeyCode::Char('[') => return UserEvent::TOGGLECARD14,
+            KeyCode::Char(']') => return UserEvent::TOGGLECARD15,
+            KeyCode::Char('\\') => return UserEvent::TOGGLECARD16,
+            KeyCode::Char(';') => return UserEvent::TOGGLECARD17,
+            KeyCode::Char('\'') => return UserEvent::TOGGLECARD18,
             KeyCode::Char('d') => return UserEvent::RESIZE,
+            KeyCode::Up => return UserEvent::UP,
+            KeyCode::Down => return UserEvent::DOWN,
+            KeyCode::Char('n') => return UserEvent::NEWTABLE,
+            KeyCode::Char('h') => return UserEvent::HINT,
             _ => return UserEvent::NOTHING,
         }
     }
@@ -323,6 +383,73 @@ pub mod display {
     // 4.         pietje2: #12 ## ## ## ## ## ## ## ## ## ## ## ## ..  €   0
     // 1.         pietje2: #13 ## ## ## ## ## ## ## ## ## ## ## ## ##  €   0
     // 2.         pietje3: #13 ## ## ## ## ## ## ## ## ## ## ## ## ##  €   0
+    // Rules: standard
+
+    // 0         1         2         3         4         5         6         7
+    // 123456789_123456789_123456789_123456789_123456789_123456789_123456789_123456789_
+    // LOBBY
+    //
+    //  1. Table   0  Round   1/8    Players 4/4  Spectators  0
+    //  2. Table   3  Waiting        Players 1/4  Spectators  0
+    //
+    // [Up/Down] Select  [Enter] Join  [n] New table  [Esc] Quit
+
+    pub fn lobby(
+        srn: &mut std::io::Stdout,
+        tlm: &network::TableListMessage,
+        selected: usize,
+    ) -> Result<()> {
+        execute!(
+            srn,
+            Clear(ClearType::All),
+            MoveTo(0, 0),
+            Print("LOBBY".white().on_dark_blue())
+        )?;
+
+        let tables = tlm.tables();
+        for (i, table) in tables.iter().enumerate() {
+            let round = if table.round == 0 {
+                String::from("Waiting      ")
+            } else {
+                format!("Round {:3}/{:<3}", table.round, table.num_rounds)
+            };
+            let s = format!(
+                "{:2}. Table {:3}  {}  Players {}/{}  Spectators {:2}",
+                i + 1,
+                table.id,
+                round,
+                table.num_players,
+                table.num_seats,
+                table.num_spectators
+            );
+            execute!(srn, MoveTo(0, 2 + i as u16))?;
+            if i == selected {
+                execute!(srn, Print(s.on_dark_green()))?;
+            } else {
+                execute!(srn, Print(s))?;
+            }
+        }
+
+        execute!(
+            srn,
+            MoveTo(0, 3 + tables.len() as u16),
+            Print("[Up/Down] Select  [Enter] Join  [n] New table  [Esc] Quit")
+        )?;
+        Ok(())
+    }
+
+    // Spectators get a label instead of the buttons.
+    pub fn draw_spectator(gs: &mut big2rules::GameState) -> Result<()> {
+        execute!(
+            gs.srn,
+            SavePosition,
+            MoveTo(43, 1),
+            Clear(ClearType::UntilNewLine),
+            Print("SPECTATING".white().on_dark_magenta()),
+            RestorePosition
+        )?;
+        Ok(())
+    }
 
     pub fn draw_btn_play(gs: &mut big2rules::GameState) -> Result<()> {
         execute!(gs.srn, SavePosition, MoveTo(43, 1))?;
@@ -366,6 +493,47 @@ pub mod display {
         Ok(())
     }
 
+    // Seconds left for the current player, None when there is no time limit. Rounds up by
+    // hand, u64::div_ceil needs Rust 1.73.
+    #[allow(clippy::manual_div_ceil)]
+    pub fn countdown_secs(gs: &big2rules::GameState) -> Option<u64> {
+        let left = gs
+            .turn_deadline?
+            .saturating_duration_since(std::time::Instant::now());
+        Some((left.as_millis() as u64 + 999) / 1000)
+    }
+
+    // Countdown next to the current player.
+    pub fn countdown(gs: &mut big2rules::GameState) -> Result<()> {
+        let secs = countdown_secs(gs);
+        let turn = gs.sm.turn;
+        let players = gs.rules.players as i32;
+        let col = 75 + extra_width(&gs.rules);
+        execute!(gs.srn, SavePosition)?;
+        for row in 0..players as u16 {
+            execute!(gs.srn, MoveTo(col, 3 + row), Print("    "))?;
+        }
+        execute!(gs.srn, RestorePosition)?;
+        if secs.is_none() || turn < 0 {
+            return Ok(());
+        }
+        let first = if gs.sm.is_spectator() {
+            0
+        } else {
+            gs.sm.your_index
+        };
+        let row = ((turn - first + players) % players) as u16;
+        let s = format!("{:3}s", secs.unwrap());
+        execute!(gs.srn, SavePosition, MoveTo(col, 3 + row))?;
+        if secs.unwrap() <= 5 {
+            execute!(gs.srn, Print(s.white().on_red()))?;
+        } else {
+            execute!(gs.srn, Print(s))?;
+        }
+        execute!(gs.srn, RestorePosition)?;
+        Ok(())
+    }
+
     pub fn cards_str(cards: u64) -> String {
         let mut bit: u64 = 1 << 11;
         let score = big2rules::rules::score_hand(cards);
@@ -392,6 +560,76 @@ pub mod display {
         return card_str;
     }
 
+    // The hinted play under the table, with the split of the rest of the hand.
+    pub fn hint(
+        srn: &mut std::io::Stdout,
+        hint: Option<u64>,
+        split: &[big2rules::cards::Combo],
+    ) -> Result<()> {
+        let play = match hint {
+            Some(cards) => cards_str(cards),
+            None => String::from("PASS "),
+        };
+        let mut rest = String::new();
+        for combo in split {
+            rest.push_str(&cards_str(combo.hand().mask()));
+            rest.push_str("| ");
+        }
+        execute!(
+            srn,
+            MoveTo(0, 8),
+            Clear(ClearType::CurrentLine),
+            Print("Hint: "),
+            Print(play),
+            Print("Then: "),
+            Print(rest)
+        )
+    }
+
+    pub fn clear_hint(srn: &mut std::io::Stdout) -> Result<()> {
+        execute!(srn, MoveTo(0, 8), Clear(ClearType::CurrentLine))
+    }
+
+    // All cards right of the table, your own and the played cards in grey. With the twos and
+    // aces that are still out. Two players leave 26 cards undealt, so the counts are at most
+    // what the other player has.
+    pub fn card_tracker(gs: &mut big2rules::GameState) -> Result<()> {
+        let col = 80 + extra_width(&gs.rules);
+        let unseen = gs.unseen_cards();
+        let hand = big2rules::cards::Hand::from_mask(unseen);
+        let twos = hand.of_rank(big2rules::cards::Rank::TWO).len();
+        let aces = hand.of_rank(big2rules::cards::Rank::ACE).len();
+        let bound = if gs.rules.players == 2 {
+            "\u{2264}"
+        } else {
+            ""
+        };
+        let s = format!("Out: 2s {}{} As {}{}", bound, twos, bound, aces);
+        execute!(gs.srn, MoveTo(col, 1), Print(s))?;
+
+        let suits = [
+            ("\u{2666}", COL_DIAMONDS),
+            ("\u{2663}", COL_CLUBS),
+            ("\u{2665}", COL_HEARTS),
+            ("\u{2660}", COL_SPADES),
+        ];
+        let rank_str: Vec<u8> = "3456789TJQKA2".into();
+        for (suit, (symbol, color)) in suits.iter().enumerate() {
+            let mut out_str = format!("{}{}{}{} ", COL_CARD_BACK, color, symbol, COL_NORMAL);
+            for (r, rank) in rank_str.iter().enumerate() {
+                let card = 1 << (12 + r * 4 + suit);
+                if unseen & card == 0 {
+                    out_str.push_str(COL_SEEN);
+                }
+                out_str.push(*rank as char);
+                out_str.push_str(COL_NORMAL);
+                out_str.push(' ');
+            }
+            execute!(gs.srn, MoveTo(col, 3 + suit as u16), Print(out_str))?;
+        }
+        Ok(())
+    }
+
     pub fn board(gs: &mut big2rules::GameState) -> Result<()> {
         let name = gs.sm.players[gs.sm.action.player as usize].name.to_string();
         let s = format!("{:>16}: ", name);
@@ -413,18 +651,32 @@ pub mod display {
         let s = format!("Rounds: {}/{}", gs.sm.round, gs.sm.num_rounds);
         execute!(gs.srn, MoveTo(0, 1), Print(s))?;
 
+        let s = format!("Rules: {}", gs.rules);
+        execute!(
+            gs.srn,
+            MoveTo(0, 7),
+            Clear(ClearType::CurrentLine),
+            Print(s)
+        )?;
+
         let cards = gs.sm.board.into_card().unwrap();
         let out_str = cards_str(cards);
         execute!(gs.srn, MoveTo(20, 1), Print("Board: "), Print(out_str))?;
 
-        let mut p = gs.sm.your_index;
-        if p < 0 || p > 3 {
-            p = 0;
-        }
+        // Spectators see the table in seat order.
+        let is_spectator = gs.sm.is_spectator();
+        let mut p = if is_spectator { 0 } else { gs.sm.your_index };
+        let players = gs.rules.players as i32;
+        let slots = hand_slots(&gs.rules);
+        let pass_col = 70 + extra_width(&gs.rules);
+
+        card_tracker(gs)?;
 
         if gs.sm.turn == -1 {
+            // Clear the countdown of the last turn.
+            countdown(gs)?;
             execute!(gs.srn, MoveTo(0, 3))?;
-            for _ in 0..4 {
+            for _ in 0..players {
                 let player = &gs.sm.players[p as usize];
                 let name = player.name.to_string();
                 let name = if name == "" {
@@ -444,23 +696,32 @@ pub mod display {
                 if player.is_ready {
                     print!(" {}READY{}", COL_BTN_PASS_AUTO, COL_NORMAL);
                 }
+                if player.is_disconnected() {
+                    print!(" {}OFFLINE{}", COL_SCORE_NEG, COL_NORMAL);
+                }
                 print!("\r\n");
                 p += 1;
-                if p == 4 {
+                if p == players {
                     p = 0;
                 };
             }
-            draw_btn_ready(gs)?;
+            if is_spectator {
+                draw_spectator(gs)?;
+            } else {
+                draw_btn_ready(gs)?;
+            }
 
             return Ok(());
         }
 
-        if p >= 0 {
+        if is_spectator {
+            draw_spectator(gs)?;
+        } else {
             draw_btn_play(gs)?;
             draw_btn_pass(gs)?;
         }
 
-        for row in 0..4 {
+        for row in 0..players as u16 {
             let player = &gs.sm.players[p as usize];
             let name = player.name.to_string();
             let name: String = if name != "" {
@@ -475,10 +736,11 @@ pub mod display {
             let n_cards: usize = player.num_cards as usize;
 
             let has_passed = player.has_passed_this_cycle;
+            let is_disconnected = player.is_disconnected();
             let player_score = player.score;
 
             if p == gs.sm.your_index {
-                let cards = gs.sm.your_hand.to_card();
+                let cards = gs.hand;
                 for bit in 12..64 {
                     let card = cards & (1 << bit);
                     if card == 0 {
@@ -503,11 +765,13 @@ pub mod display {
             } else {
                 out_str = format!("{}##{} ", COL_CARD_BACK, COL_NORMAL).repeat(n_cards);
             }
-            let no_cards = ".. ".to_string().repeat(13 - n_cards);
+            let no_cards = ".. ".to_string().repeat(slots.saturating_sub(n_cards));
 
             // Number and Names.
             execute!(gs.srn, MoveTo(0, 3 + row), Print(format!("{}.", p + 1)),)?;
-            if p == gs.sm.turn {
+            if is_disconnected {
+                execute!(gs.srn, Print(s.on_dark_red()))?;
+            } else if p == gs.sm.turn {
                 execute!(gs.srn, Print(s.on_dark_green()))?;
             } else if has_passed {
                 execute!(gs.srn, Print(s.on_dark_grey()))?;
@@ -527,16 +791,18 @@ pub mod display {
             if has_passed {
                 execute!(
                     gs.srn,
-                    MoveTo(70, 3 + row),
+                    MoveTo(pass_col, 3 + row),
                     Print("PASS".white().on_dark_grey()),
                 )?;
             }
             p += 1;
-            if p == 4 {
+            if p == players {
                 p = 0;
             };
         }
 
+        countdown(gs)?;
+
         // // Debug Text
         // execute!(
         //     gs.srn,
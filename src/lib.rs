@@ -1,4 +1,5 @@
 // This lines exports the modules for tests/ and benches/
+pub mod arena;
 pub mod big2rules;
 pub mod cli;
 pub mod network;
@@ -1,6 +1,4 @@
-mod big2rules;
-mod cli;
-mod network;
+use big2::{arena, big2rules, cli, network};
 
 use std::{fs::File, thread, time};
 
@@ -13,11 +11,15 @@ use simplelog::*;
 
 use pico_args::{Arguments, Error as paError};
 
+const LOBBY_REFRESH: time::Duration = time::Duration::from_secs(1);
+const LOBBY_TIMEOUT: time::Duration = time::Duration::from_secs(2);
+
 #[derive(Debug, PartialEq)]
 enum AppMode {
     HOSTONLY,
     HOST,
     CLIENT,
+    ARENA,
 }
 
 #[derive(Debug, PartialEq)]
@@ -27,7 +29,17 @@ struct CliArgs {
     socket_addr: String,
     rounds: u8,
     host_port: u16,
-    auto_play: bool,
+    matches: u32,
+    // The strategy that plays for you.
+    auto_play: Option<big2rules::bot::Strategy>,
+    spectate: bool,
+    turn_time: u8,
+    bots: Option<big2rules::bot::Strategy>,
+    bot_name: String,
+    rules: big2rules::rules::RuleSet,
+    seed: Option<u64>,
+    // A bot strategy for every seat of the arena.
+    arena: Vec<big2rules::bot::Strategy>,
 }
 
 fn parse_args(mut args: Arguments) -> Result<CliArgs, paError> {
@@ -37,8 +49,26 @@ fn parse_args(mut args: Arguments) -> Result<CliArgs, paError> {
         socket_addr: String::from(""),
         rounds: 8,
         host_port: network::common::PORT,
-        auto_play: args.contains("-auto-play"),
+        matches: 0,
+        auto_play: None,
+        spectate: args.contains("-spectate"),
+        turn_time: 0,
+        bots: None,
+        bot_name: String::from("Bot"),
+        rules: big2rules::rules::RuleSet::default(),
+        seed: None,
+        arena: Vec::new(),
+    };
+
+    // A bare -auto-play, without a strategy after it, keeps the lowest strategy.
+    cli_args.auto_play = match args.opt_value_from_str("-auto-play") {
+        Err(paError::OptionWithoutAValue(_)) => None,
+        Err(paError::Utf8ArgumentParsingFailed { value, .. }) if value.starts_with('-') => None,
+        ret => ret?,
     };
+    if args.contains("-auto-play") {
+        cli_args.auto_play = Some(big2rules::bot::Strategy::Lowest);
+    }
 
     let join: Option<String> = args.opt_value_from_str("-join")?;
 
@@ -48,13 +78,28 @@ fn parse_args(mut args: Arguments) -> Result<CliArgs, paError> {
 
     let be_hostonly = args.contains("-host-only");
 
+    let arena: Option<String> = args.opt_value_from_str("-arena")?;
+    let be_arena = arena.is_some();
+
     if join.is_some() && (be_host || be_hostonly) {
         return Err(paError::ArgumentParsingFailed {
             cause: "-join combined with -host or -host-only is now allowed.".to_string(),
         });
     }
 
-    if (join.is_some() || be_host) && name.is_none() {
+    if be_arena && (join.is_some() || be_host || be_hostonly) {
+        return Err(paError::ArgumentParsingFailed {
+            cause: "-arena can't be combined with -join, -host or -host-only.".to_string(),
+        });
+    }
+
+    if cli_args.spectate && join.is_none() {
+        return Err(paError::ArgumentParsingFailed {
+            cause: "-spectate is missing -join".to_string(),
+        });
+    }
+
+    if ((join.is_some() && !cli_args.spectate) || be_host) && name.is_none() {
         return Err(paError::ArgumentParsingFailed {
             cause: "-join or -host is missing -name".to_string(),
         });
@@ -68,6 +113,16 @@ fn parse_args(mut args: Arguments) -> Result<CliArgs, paError> {
         cli_args.app_mode = AppMode::HOSTONLY;
     }
 
+    if let Some(arena) = arena {
+        for name in arena.split(',') {
+            let strategy = name
+                .parse()
+                .map_err(|cause| paError::ArgumentParsingFailed { cause })?;
+            cli_args.arena.push(strategy);
+        }
+        cli_args.app_mode = AppMode::ARENA;
+    }
+
     if let Some(name) = name {
         if name.len() < 1 || name.len() > 16 {
             return Err(paError::ArgumentParsingFailed {
@@ -95,12 +150,62 @@ fn parse_args(mut args: Arguments) -> Result<CliArgs, paError> {
         }
     }
 
-    if be_host {
-        let value: Option<u8> = args.opt_value_from_str("-rounds")?;
-        cli_args.rounds = value.unwrap_or(8);
+    // -join uses the rounds for the tables it creates in the lobby.
+    let value: Option<u8> = args.opt_value_from_str("-rounds")?;
+    cli_args.rounds = value.unwrap_or(8);
 
+    if be_host || be_hostonly {
         let value: Option<u16> = args.opt_value_from_str("-port")?;
         cli_args.host_port = value.unwrap_or(network::common::PORT);
+
+        let value: Option<u8> = args.opt_value_from_str("-turn-time")?;
+        cli_args.turn_time = value.unwrap_or(0);
+
+        cli_args.bots = args.opt_value_from_str("-bots")?;
+
+        let value: Option<String> = args.opt_value_from_str("-bot-name")?;
+        if let Some(name) = value {
+            // Room for the seat number.
+            if name.is_empty() || name.len() > 15 || name.contains(' ') {
+                return Err(paError::ArgumentParsingFailed {
+                    cause: "Bot name length min 1 max 15 bytes, no spaces!".to_string(),
+                });
+            }
+            cli_args.bot_name = name;
+        }
+    }
+
+    if be_host || be_hostonly || be_arena {
+        let value: Option<big2rules::rules::RuleSet> = args.opt_value_from_str("-rules")?;
+        cli_args.rules = value.unwrap_or_default();
+
+        let value: Option<u8> = args.opt_value_from_str("-players")?;
+        if let Some(players) = value {
+            if !(2..=4).contains(&players) {
+                return Err(paError::ArgumentParsingFailed {
+                    cause: "Players min 2 max 4!".to_string(),
+                });
+            }
+            cli_args.rules.players = players;
+        }
+        if let Err(cause) = cli_args.rules.check() {
+            return Err(paError::ArgumentParsingFailed { cause });
+        }
+
+        cli_args.seed = args.opt_value_from_str("-seed")?;
+    }
+
+    if be_hostonly || be_arena {
+        let value: Option<u32> = args.opt_value_from_str("-matches")?;
+        // The arena can't play forever.
+        let default = if be_arena { 1000 } else { 0 };
+        cli_args.matches = value.unwrap_or(default);
+    }
+
+    if be_arena && cli_args.arena.len() != cli_args.rules.players as usize {
+        return Err(paError::ArgumentParsingFailed {
+            cause: "-arena needs a strategy for every seat".to_string(),
+        });
     }
 
     args.finish()?;
@@ -108,15 +213,107 @@ fn parse_args(mut args: Arguments) -> Result<CliArgs, paError> {
     Ok(cli_args)
 }
 
+// Let the user pick or create a table. Returns None when the user quits. A server without a
+// lobby, like the Muon version, doesn't answer. Then join the only table it has.
+fn lobby(
+    srn: &mut std::io::Stdout,
+    ts: &mut network::client::TcpClient,
+    rounds: u8,
+) -> Result<Option<u32>, std::io::Error> {
+    let start = time::Instant::now();
+    let mut refresh: Option<time::Instant> = None;
+    let mut tables: Option<network::TableListMessage> = None;
+    let mut selected: usize = 0;
+
+    loop {
+        if !matches!(refresh, Some(r) if r.elapsed() < LOBBY_REFRESH) {
+            ts.send_list_tables_msg()?;
+            refresh = Some(time::Instant::now());
+        }
+
+        let mut redraw = false;
+        ts.check_buffer()?;
+        if let Some(tlm) = ts.table_list() {
+            // Select the table we just created.
+            if let Some(t) = tlm
+                .tables()
+                .iter()
+                .position(|t| t.id as i32 == tlm.your_table)
+            {
+                selected = t;
+            }
+            tables = Some(tlm);
+            redraw = true;
+        }
+
+        let tlm = match &tables {
+            None if start.elapsed() > LOBBY_TIMEOUT => {
+                info!("LOBBY: No table list, join the default table");
+                return Ok(Some(network::common::DEFAULT_TABLE));
+            }
+            None => {
+                thread::sleep(time::Duration::from_millis(10));
+                continue;
+            }
+            Some(tlm) => tlm,
+        };
+        let count = tlm.tables().len();
+
+        match cli::display::poll_user_events() {
+            cli::display::UserEvent::QUIT => return Ok(None),
+            cli::display::UserEvent::PLAY if selected < count => {
+                return Ok(Some(tlm.tables()[selected].id));
+            }
+            cli::display::UserEvent::NEWTABLE => {
+                ts.send_create_table_msg(rounds)?;
+            }
+            cli::display::UserEvent::UP if selected > 0 => {
+                selected -= 1;
+                redraw = true;
+            }
+            cli::display::UserEvent::DOWN if selected + 1 < count => {
+                selected += 1;
+                redraw = true;
+            }
+            cli::display::UserEvent::RESIZE => redraw = true,
+            _ => (),
+        }
+
+        if redraw {
+            if let Err(e) = cli::display::lobby(srn, tlm, selected) {
+                error!("DISPLAY ERROR {}", e);
+            }
+        }
+    }
+}
+
 fn main() {
     let cli_args = parse_args(Arguments::from_env());
     if let Err(e) = cli_args {
         println!("Invalid arguments! {:?}", e);
         std::process::exit(1);
     }
-    let cli_args = cli_args.unwrap();
+    let mut cli_args = cli_args.unwrap();
+
+    if cli_args.app_mode == AppMode::ARENA {
+        // No log, the rules engine logs every move.
+        let seed = cli_args.seed.unwrap_or_else(rand::random);
+        let arena = arena::Arena {
+            rules: cli_args.rules,
+            rounds: cli_args.rounds,
+            strategies: cli_args.arena,
+        };
+        println!(
+            "Arena: rules {} rounds {} matches {} seed {}",
+            arena.rules, arena.rounds, cli_args.matches, seed
+        );
+        println!("{}", arena.run(cli_args.matches, seed));
+        return;
+    }
 
-    let logfilename = if cli_args.app_mode == AppMode::CLIENT {
+    let logfilename = if cli_args.app_mode == AppMode::CLIENT && cli_args.name.is_empty() {
+        String::from("spectator.log")
+    } else if cli_args.app_mode == AppMode::CLIENT {
         format!("{}.log", &cli_args.name)
     } else {
         String::from("big2.log")
@@ -129,28 +326,57 @@ fn main() {
     );
 
     if cli_args.app_mode == AppMode::HOSTONLY {
-        let mut srv = big2rules::SrvGameState::new(cli_args.rounds);
-
-        srv.deal(None);
-
-        println!("Start game {}/{}", srv.round, srv.rounds);
-
-        srv.play(srv.turn, 0x1000).unwrap();
-
-        srv.pass(srv.turn).unwrap();
-
-        println!("{}", srv.turn);
-
-        error!("Currently not supported!");
-        std::process::exit(1);
+        // Headless, only log to the logfile. There is no terminal to write to.
+        info!(
+            "Host-only: port {} rounds {} matches {} turn time {} rules {} seed {:?}",
+            cli_args.host_port,
+            cli_args.rounds,
+            cli_args.matches,
+            cli_args.turn_time,
+            cli_args.rules,
+            cli_args.seed
+        );
+        let srv = network::server::GameServer::bind(
+            cli_args.host_port,
+            cli_args.rounds,
+            cli_args.matches,
+        );
+        match srv {
+            Err(e) => {
+                error!("Can't start server: {}", e);
+                std::process::exit(1);
+            }
+            Ok(mut srv) => {
+                srv.set_turn_time(cli_args.turn_time);
+                srv.set_bots(cli_args.bots, &cli_args.bot_name);
+                srv.set_rules(cli_args.rules);
+                srv.set_seed(cli_args.seed);
+                srv.run();
+            }
+        }
+        info!("Host-only: shutdown");
+        return;
     }
 
     if cli_args.app_mode == AppMode::HOST {
-        error!("Currently not supported!");
-        std::process::exit(1);
+        let srv = network::server::GameServer::bind(cli_args.host_port, cli_args.rounds, 0);
+        let srv = srv.and_then(|mut srv| {
+            srv.set_turn_time(cli_args.turn_time);
+            srv.set_bots(cli_args.bots, &cli_args.bot_name);
+            srv.set_rules(cli_args.rules);
+            srv.set_seed(cli_args.seed);
+            srv.spawn()
+        });
+        if let Err(e) = srv {
+            error!("Can't start server: {}", e);
+            println!("Can't start server on port {}: {}", cli_args.host_port, e);
+            std::process::exit(1);
+        }
+        // The host joins its own table as a normal player.
+        cli_args.socket_addr = format!("127.0.0.1:{}", cli_args.host_port);
     }
 
-    if cli_args.app_mode == AppMode::CLIENT {
+    if cli_args.app_mode == AppMode::CLIENT || cli_args.app_mode == AppMode::HOST {
         let l = cli_args.socket_addr.len();
         let title = format!(
             "Name: {} Table {}",
@@ -158,7 +384,7 @@ fn main() {
             &cli_args.socket_addr.get(l - 1..l).unwrap()
         );
 
-        let srn = cli::display::init(&title).unwrap();
+        let mut srn = cli::display::init(&title).unwrap();
 
         let client = network::client::TcpClient::connect(cli_args.socket_addr);
 
@@ -170,7 +396,36 @@ fn main() {
 
         let mut ts = client.unwrap();
 
-        if let Err(e) = ts.send_join_msg(&cli_args.name) {
+        // The host and auto-play clients skip the lobby and go to the default table.
+        let table = if cli_args.app_mode == AppMode::CLIENT && cli_args.auto_play.is_none() {
+            lobby(&mut srn, &mut ts, cli_args.rounds)
+        } else {
+            ts.detect_lobby(LOBBY_TIMEOUT)
+                .map(|_| Some(network::common::DEFAULT_TABLE))
+        };
+        let table = match table {
+            Err(e) => {
+                let _ = cli::display::close(srn);
+                print!("{}\r\n", e);
+                std::process::exit(1);
+            }
+            Ok(None) => {
+                network::client::disconnect(ts);
+                let _ = cli::display::close(srn);
+                return;
+            }
+            Ok(Some(table)) => table,
+        };
+        if let Err(e) = cli::display::clear(&mut srn) {
+            error!("DISPLAY ERROR {}", e);
+        }
+
+        let ret = if cli_args.spectate {
+            ts.send_spectate_msg(table)
+        } else {
+            ts.send_join_table_msg(&cli_args.name, table)
+        };
+        if let Err(e) = ret {
             let _ = cli::display::close(srn);
             print!("{}\r\n", e);
             std::process::exit(1);
@@ -186,13 +441,24 @@ fn main() {
             is_valid_hand: false,
             hand_score: 0,
             sm: network::StateMessage::new(None),
+            turn_deadline: None,
+            rules: big2rules::rules::RuleSet::default(),
+            hand: 0,
+            played_cards: 0,
         };
+        let mut countdown: Option<u64> = None;
+        // The bot plays for you for the whole game, so it keeps its state.
+        let seed = cli_args.seed.unwrap_or_else(rand::random);
+        let mut auto_play = cli_args.auto_play.map(|strategy| strategy.bot(seed));
 
         // Game loop
+        let mut exit_error: Option<std::io::Error> = None;
+
         'gameloop: loop {
             let ret = ts.check_buffer();
             if let Err(e) = ret {
                 error!("Error: TCPStream: {:?}", e);
+                exit_error = Some(e);
                 break 'gameloop;
             }
             let buffer_sm = ret.unwrap();
@@ -200,7 +466,24 @@ fn main() {
             // Process new StateMessage
             if buffer_sm.is_some() {
                 gs.sm = buffer_sm.unwrap();
+                gs.hand = ts.take_hand().unwrap_or_else(|| gs.sm.your_hand.to_card());
+                let rules = ts.rules().unwrap_or_default();
+                if rules.players != gs.rules.players {
+                    if let Err(e) = cli::display::resize(&mut gs.srn, &rules) {
+                        error!("DISPLAY ERROR {}", e);
+                    }
+                }
+                gs.rules = rules;
+                // The hint was for the last state.
+                if let Err(e) = cli::display::clear_hint(&mut gs.srn) {
+                    error!("DISPLAY ERROR {}", e);
+                }
+                gs.turn_deadline = match gs.sm.action.turn_time {
+                    0 => None,
+                    secs => Some(time::Instant::now() + time::Duration::from_secs(secs as u64)),
+                };
                 trace!("TRAIL: {:16x}h", gs.sm.action_msg());
+                gs.track_played_cards();
                 match gs.sm.action.action_type {
                     network::StateMessageActionType::PLAY => {
                         let p = gs.sm.action.player;
@@ -223,13 +506,17 @@ fn main() {
                     }
                     network::StateMessageActionType::DEAL => {
                         trace!("PLAY: DEAL: ROUND {}/{}", gs.sm.round, gs.sm.num_rounds);
+                        if let Some(win) = gs.sm.instant_win() {
+                            let name = gs.sm.player_name(gs.sm.action.player);
+                            trace!("PLAY: {:>16}: WINS WITH {}", name.unwrap_or_default(), win);
+                        }
                     }
                 };
                 if gs.sm.turn == -1 {
                     let mut dscore = Vec::<i16>::with_capacity(4);
                     let mut cardnum = Vec::<u8>::with_capacity(4);
                     let mut out = String::with_capacity(256);
-                    for p in 0..4 {
+                    for p in 0..gs.rules.players as usize {
                         let score = gs.sm.players[p].delta_score;
                         let name = gs.sm.players[p].name.to_string();
                         dscore.push(score as i16);
@@ -245,10 +532,14 @@ fn main() {
                 }
 
                 let next_str: String = if gs.sm.turn == -1 {
-                    if gs.sm.round == gs.sm.num_rounds {
-                        String::from("The END!")
+                    let end = if gs.sm.round == gs.sm.num_rounds {
+                        "The END!"
                     } else {
-                        String::from("Waiting for users ready")
+                        "Waiting for users ready"
+                    };
+                    match (gs.sm.instant_win(), gs.sm.player_name(gs.sm.action.player)) {
+                        (Some(win), Some(name)) => format!("{} wins with {}! {}", name, win, end),
+                        _ => String::from(end),
                     }
                 } else {
                     let name = gs.sm.current_player_name();
@@ -271,7 +562,7 @@ fn main() {
                     if let Err(e) = cli::display::board(&mut gs) {
                         error!("DISPLAY ERROR {}", e);
                     }
-                    let delay = if cli_args.auto_play == false {
+                    let delay = if cli_args.auto_play.is_none() {
                         1000
                     } else {
                         10
@@ -299,8 +590,8 @@ fn main() {
                     if gs.sm.action.is_end_of_cycle {
                         // Clear auto_pass and players[x].hasPassed.
                         gs.auto_pass = false;
-                        for p in 0..4 {
-                            gs.sm.players[p].has_passed_this_cycle = false;
+                        for p in gs.sm.players.iter_mut() {
+                            p.has_passed_this_cycle = false;
                         }
                         // Clear board and scores.
                         gs.sm.board = network::muon::InlineList8 {
@@ -314,7 +605,8 @@ fn main() {
                         if gs.sm.turn != gs.sm.your_index {
                             gs.cards_selected = 0;
                         }
-                        gs.hand_score = big2rules::rules::score_hand(gs.cards_selected);
+                        gs.hand_score =
+                            big2rules::rules::score_hand_with(gs.cards_selected, &gs.rules);
                         if let Err(e) = cli::display::clear(&mut gs.srn) {
                             error!("DISPLAY ERROR {}", e);
                         }
@@ -336,10 +628,8 @@ fn main() {
 
                 if gs.sm.action.action_type == network::StateMessageActionType::UPDATE {
                     gs.board = gs.sm.board.into_card().unwrap();
-                    gs.board_score = big2rules::rules::score_hand(gs.board);
-                    gs.is_valid_hand = (gs.hand_score > gs.board_score)
-                        && (gs.board == 0
-                            || gs.board.count_ones() == gs.cards_selected.count_ones());
+                    gs.board_score = big2rules::rules::score_hand_with(gs.board, &gs.rules);
+                    gs.is_valid_hand = gs.rules.can_play(gs.board, gs.cards_selected);
 
                     if let Err(e) = cli::display::board(&mut gs) {
                         error!("DISPLAY ERROR {}", e);
@@ -348,8 +638,9 @@ fn main() {
 
                 // println!("\n\n\r\n## B 0x{:16x} T {:2} ##", gs.board, gs.sm.turn);
                 // Auto play
-                if cli_args.auto_play {
-                    for p in gs.sm.players.iter() {
+                let bot = auto_play.as_mut().filter(|_| !gs.sm.is_spectator());
+                if let Some(bot) = bot {
+                    for p in gs.sm.players.iter().take(gs.rules.players as usize) {
                         if p.name.count == 0 {
                             continue 'gameloop;
                         }
@@ -365,25 +656,24 @@ fn main() {
                         }
                     }
                     if gs.sm.turn == gs.sm.your_index {
-                        if gs.sm.board.count > 1 {
-                            let _ = ts.action_pass();
-                        }
-                        let hand = gs.sm.your_hand.to_card();
-                        let better_card = big2rules::rules::higher_single_card(gs.board, hand);
-                        // println!(
-                        //     "\n\n\r\n-- B 0x{:16x} H 0x{:16x} C 0x{:16x} --",
-                        //     gs.board, hand, better_card
-                        // );
-                        if better_card == 0 {
-                            let _ = ts.action_pass();
-                        } else {
-                            let _ = ts.action_play(better_card);
-                        }
+                        let _ = match bot.play(&gs.view()) {
+                            None => ts.action_pass(),
+                            Some(cards) => ts.action_play(cards),
+                        };
                         continue;
                     }
                 }
             }
 
+            // Update the countdown of the current player every second.
+            let secs = cli::display::countdown_secs(&gs);
+            if secs != countdown {
+                countdown = secs;
+                if let Err(e) = cli::display::countdown(&mut gs) {
+                    error!("DISPLAY ERROR {}", e);
+                }
+            }
+
             // Poll user events
             let user_event = cli::display::poll_user_events();
             if user_event != cli::display::UserEvent::NOTHING {
@@ -404,6 +694,11 @@ fn main() {
                     break;
                 }
 
+                // Spectators can only watch.
+                if gs.sm.is_spectator() {
+                    continue;
+                }
+
                 let is_inbetween: bool = gs.sm.turn == -1;
 
                 // Ready
@@ -456,6 +751,21 @@ fn main() {
                     if user_event == cli::display::UserEvent::TOGGLECARD13 {
                         toggle_card = 13;
                     }
+                    if user_event == cli::display::UserEvent::TOGGLECARD14 {
+                        toggle_card = 14;
+                    }
+                    if user_event == cli::display::UserEvent::TOGGLECARD15 {
+                        toggle_card = 15;
+                    }
+                    if user_event == cli::display::UserEvent::TOGGLECARD16 {
+                        toggle_card = 16;
+                    }
+                    if user_event == cli::display::UserEvent::TOGGLECARD17 {
+                        toggle_card = 17;
+                    }
+                    if user_event == cli::display::UserEvent::TOGGLECARD18 {
+                        toggle_card = 18;
+                    }
                     if user_event == cli::display::UserEvent::CLEAR && gs.cards_selected != 0 {
                         gs.cards_selected = 0;
                         gs.hand_score = 0;
@@ -469,20 +779,40 @@ fn main() {
                     let is_your_turn: bool = gs.sm.turn == me_index;
 
                     if toggle_card != 0 {
-                        if toggle_card > gs.sm.your_hand.count as usize {
-                            continue;
-                        }
-                        let card =
-                            network::muon::card_from_byte(gs.sm.your_hand.data[toggle_card - 1]);
+                        let hand = big2rules::cards::Hand::from_mask(gs.hand);
+                        let card = match hand.iter().nth(toggle_card - 1) {
+                            None => continue,
+                            Some(card) => card.mask(),
+                        };
                         gs.cards_selected ^= card;
-                        gs.hand_score = big2rules::rules::score_hand(gs.cards_selected);
-                        gs.is_valid_hand = is_your_turn
-                            && (gs.hand_score > gs.board_score)
-                            && (gs.board == 0
-                                || gs.board.count_ones() == gs.cards_selected.count_ones());
+                        gs.hand_score =
+                            big2rules::rules::score_hand_with(gs.cards_selected, &gs.rules);
+                        gs.is_valid_hand =
+                            is_your_turn && gs.rules.can_play(gs.board, gs.cards_selected);
+                        if let Err(e) = cli::display::board(&mut gs) {
+                            error!("DISPLAY ERROR {}", e);
+                        }
+                    }
+
+                    // Select the hinted play.
+                    if user_event == cli::display::UserEvent::HINT && is_your_turn {
+                        let hint = big2rules::hint::hint(&gs.view());
+                        let unseen = big2rules::cards::ALL & !gs.hand & !gs.played_cards;
+                        let split = big2rules::hint::partition(
+                            gs.hand ^ hint.unwrap_or(0),
+                            unseen,
+                            &gs.rules,
+                        );
+                        gs.cards_selected = hint.unwrap_or(0);
+                        gs.hand_score =
+                            big2rules::rules::score_hand_with(gs.cards_selected, &gs.rules);
+                        gs.is_valid_hand = gs.rules.can_play(gs.board, gs.cards_selected);
                         if let Err(e) = cli::display::board(&mut gs) {
                             error!("DISPLAY ERROR {}", e);
                         }
+                        if let Err(e) = cli::display::hint(&mut gs.srn, hint, &split) {
+                            error!("DISPLAY ERROR {}", e);
+                        }
                     }
 
                     let you = &gs.sm.players[me_index as usize];
@@ -527,6 +857,11 @@ fn main() {
 
         // close cli right way
         let _ = cli::display::close(gs.srn);
+
+        if let Some(e) = exit_error {
+            print!("{}\r\n", e);
+            std::process::exit(1);
+        }
     }
 }
 
@@ -551,7 +886,15 @@ mod tests {
             socket_addr: String::from("10.10.10.10:27191"),
             rounds: 8,
             host_port: 27191,
-            auto_play: false,
+            matches: 0,
+            auto_play: None,
+            spectate: false,
+            turn_time: 0,
+            bots: None,
+            bot_name: String::from("Bot"),
+            rules: big2rules::rules::RuleSet::default(),
+            seed: None,
+            arena: Vec::new(),
         };
         assert_eq!(ar, ans);
     }
@@ -566,7 +909,15 @@ mod tests {
             socket_addr: String::from(""),
             rounds: 8,
             host_port: 27191,
-            auto_play: false,
+            matches: 0,
+            auto_play: None,
+            spectate: false,
+            turn_time: 0,
+            bots: None,
+            bot_name: String::from("Bot"),
+            rules: big2rules::rules::RuleSet::default(),
+            seed: None,
+            arena: Vec::new(),
         };
         assert_eq!(ar, ans);
     }
@@ -580,11 +931,247 @@ mod tests {
             socket_addr: String::from(""),
             rounds: 10,
             host_port: 27191,
-            auto_play: false,
+            matches: 0,
+            auto_play: None,
+            spectate: false,
+            turn_time: 0,
+            bots: None,
+            bot_name: String::from("Bot"),
+            rules: big2rules::rules::RuleSet::default(),
+            seed: None,
+            arena: Vec::new(),
         };
         assert_eq!(ar, ans);
     }
 
+    #[test]
+    fn argument_test_host_only() {
+        let args = Arguments::from_vec(to_vec(&[
+            "-host-only",
+            "-rounds",
+            "4",
+            "-port",
+            "1234",
+            "-matches",
+            "2",
+            "-turn-time",
+            "30",
+        ]));
+        let ar = parse_args(args).unwrap();
+        let ans = CliArgs {
+            name: String::from(""),
+            app_mode: AppMode::HOSTONLY,
+            socket_addr: String::from(""),
+            rounds: 4,
+            host_port: 1234,
+            matches: 2,
+            auto_play: None,
+            spectate: false,
+            turn_time: 30,
+            bots: None,
+            bot_name: String::from("Bot"),
+            rules: big2rules::rules::RuleSet::default(),
+            seed: None,
+            arena: Vec::new(),
+        };
+        assert_eq!(ar, ans);
+    }
+
+    #[test]
+    fn argument_test_host_bots() {
+        let args = Arguments::from_vec(to_vec(&[
+            "-host",
+            "-name",
+            "Kim",
+            "-bots",
+            "lowest",
+            "-bot-name",
+            "Robo",
+        ]));
+        let ar = parse_args(args).unwrap();
+        let ans = CliArgs {
+            name: String::from("Kim"),
+            app_mode: AppMode::HOST,
+            socket_addr: String::from(""),
+            rounds: 8,
+            host_port: network::common::PORT,
+            matches: 0,
+            auto_play: None,
+            spectate: false,
+            turn_time: 0,
+            bots: Some(big2rules::bot::Strategy::Lowest),
+            bot_name: String::from("Robo"),
+            rules: big2rules::rules::RuleSet::default(),
+            seed: None,
+            arena: Vec::new(),
+        };
+        assert_eq!(ar, ans);
+    }
+
+    #[test]
+    fn argument_test_host_rules() {
+        let args = Arguments::from_vec(to_vec(&["-host-only", "-rules", "clubs-low"]));
+        let ar = parse_args(args).unwrap();
+        assert_eq!(
+            ar.rules,
+            big2rules::rules::RuleSet::preset("clubs-low").unwrap()
+        );
+
+        let args = Arguments::from_vec(to_vec(&["-host-only", "-rules", "house"]));
+        assert!(parse_args(args).is_err());
+    }
+
+    #[test]
+    fn argument_test_host_players() {
+        let args = Arguments::from_vec(to_vec(&[
+            "-host-only",
+            "-rules",
+            "no-twos",
+            "-players",
+            "3",
+        ]));
+        let ar = parse_args(args).unwrap();
+        assert_eq!(ar.rules.players, 3);
+        assert_eq!(ar.rules.name(), "no-twos");
+
+        let args = Arguments::from_vec(to_vec(&["-host", "-name", "Kim", "-players", "5"]));
+        assert!(parse_args(args).is_err());
+    }
+
+    #[test]
+    fn argument_test_host_seed() {
+        let args = Arguments::from_vec(to_vec(&["-host-only", "-seed", "1234"]));
+        assert_eq!(parse_args(args).unwrap().seed, Some(1234));
+
+        let args = Arguments::from_vec(to_vec(&["-host-only", "-seed", "-1"]));
+        assert!(parse_args(args).is_err());
+    }
+
+    #[test]
+    fn argument_test_auto_play() {
+        let args = Arguments::from_vec(to_vec(&[
+            "-auto-play",
+            "-join",
+            "127.0.0.1",
+            "-name",
+            "Kim",
+        ]));
+        let ar = parse_args(args).unwrap();
+        assert_eq!(ar.auto_play, Some(big2rules::bot::Strategy::Lowest));
+
+        let args = Arguments::from_vec(to_vec(&[
+            "-join",
+            "127.0.0.1",
+            "-name",
+            "Kim",
+            "-auto-play=heuristic",
+        ]));
+        let ar = parse_args(args).unwrap();
+        assert_eq!(ar.auto_play, Some(big2rules::bot::Strategy::Heuristic));
+
+        let args = Arguments::from_vec(to_vec(&[
+            "-join",
+            "127.0.0.1",
+            "-name",
+            "Kim",
+            "-auto-play",
+            "heuristic",
+        ]));
+        let ar = parse_args(args).unwrap();
+        assert_eq!(ar.auto_play, Some(big2rules::bot::Strategy::Heuristic));
+
+        let args = Arguments::from_vec(to_vec(&["-host", "-name", "Kim", "-auto-play"]));
+        let ar = parse_args(args).unwrap();
+        assert_eq!(ar.auto_play, Some(big2rules::bot::Strategy::Lowest));
+
+        let args = Arguments::from_vec(to_vec(&["-host", "-name", "Kim", "-auto-play=smart"]));
+        assert!(parse_args(args).is_err());
+        let args = Arguments::from_vec(to_vec(&["-host", "-name", "Kim", "-auto-play", "smart"]));
+        assert!(parse_args(args).is_err());
+    }
+
+    #[test]
+    fn argument_test_arena() {
+        use big2rules::bot::Strategy;
+
+        let args = Arguments::from_vec(to_vec(&["-arena", "ismcts,greedy,greedy,random"]));
+        let ar = parse_args(args).unwrap();
+        assert_eq!(ar.app_mode, AppMode::ARENA);
+        assert_eq!(
+            ar.arena,
+            vec![
+                Strategy::Ismcts,
+                Strategy::Greedy,
+                Strategy::Greedy,
+                Strategy::Random
+            ]
+        );
+        assert_eq!(ar.matches, 1000);
+
+        let args = Arguments::from_vec(to_vec(&[
+            "-arena",
+            "heuristic,lowest,passive",
+            "-players",
+            "3",
+            "-matches",
+            "20",
+            "-seed",
+            "7",
+        ]));
+        let ar = parse_args(args).unwrap();
+        assert_eq!(ar.rules.players, 3);
+        assert_eq!(ar.matches, 20);
+        assert_eq!(ar.seed, Some(7));
+
+        // A strategy for every seat.
+        let args = Arguments::from_vec(to_vec(&["-arena", "greedy,random"]));
+        assert!(parse_args(args).is_err());
+        let args = Arguments::from_vec(to_vec(&["-arena", "greedy,smart,random,lowest"]));
+        assert!(parse_args(args).is_err());
+        let args = Arguments::from_vec(to_vec(&[
+            "-arena",
+            "greedy,greedy,random,lowest",
+            "-host-only",
+        ]));
+        assert!(parse_args(args).is_err());
+    }
+
+    #[test]
+    fn argument_test_host_bots_invalid() {
+        let args = Arguments::from_vec(to_vec(&["-host", "-name", "Kim", "-bots", "smart"]));
+        assert!(parse_args(args).is_err());
+    }
+
+    #[test]
+    fn argument_test_spectate() {
+        let args = Arguments::from_vec(to_vec(&["-join", "127.0.0.1", "-spectate"]));
+        let ar = parse_args(args).unwrap();
+        let ans = CliArgs {
+            name: String::from(""),
+            app_mode: AppMode::CLIENT,
+            socket_addr: String::from("127.0.0.1:27191"),
+            rounds: 8,
+            host_port: network::common::PORT,
+            matches: 0,
+            auto_play: None,
+            spectate: true,
+            turn_time: 0,
+            bots: None,
+            bot_name: String::from("Bot"),
+            rules: big2rules::rules::RuleSet::default(),
+            seed: None,
+            arena: Vec::new(),
+        };
+        assert_eq!(ar, ans);
+    }
+
+    #[test]
+    fn argument_test_spectate_no_join() {
+        let args = Arguments::from_vec(to_vec(&["-host", "-name", "Kim", "-spectate"]));
+        let ar = parse_args(args);
+        assert!(ar.is_err());
+    }
+
     // Invalid argument tests
 
     #[test]
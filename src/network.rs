@@ -4,15 +4,16 @@ use serde::{Deserialize, Serialize};
 
 use std::{
     convert::TryFrom,
+    fmt,
     io::{self, Read, Write},
     mem,
     net::{TcpStream, ToSocketAddrs},
     sync::mpsc::{Receiver, Sender},
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
 pub enum StateMessageActionType {
     UPDATE = 0,
     DEAL = 1,
@@ -44,6 +45,128 @@ pub struct JoinMessage {
     magicnumber: u32,
     version: u32,
     name: muon::String16,
+    // Not part of the Muon version, its join message ends after the name.
+    session: u32,
+    table: u32,
+}
+
+impl JoinMessage {
+    // The size of the Muon join message, without session and table.
+    pub const MUON_SIZE: usize = mem::size_of::<JoinMessage>() - 2 * mem::size_of::<u32>();
+}
+
+// Not part of the Muon version. Lobby messages to pick a table on a server with many tables.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SpectateMessage {
+    pub kind: u32,
+    pub size: u32,
+    pub table: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateTableMessage {
+    pub kind: u32,
+    pub size: u32,
+    pub rounds: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+pub struct TableInfo {
+    pub id: u32,
+    pub round: u32,
+    pub num_rounds: u32,
+    pub num_players: u32,
+    pub num_seats: u32,
+    pub num_spectators: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TableListMessage {
+    pub kind: u32,
+    pub size: u32,
+    pub count: u32,
+    pub your_table: i32, // Table created by you, -1 if none
+    pub tables: [TableInfo; common::MAX_TABLES],
+}
+
+impl TableListMessage {
+    pub fn new() -> Self {
+        Self {
+            kind: 9,
+            size: mem::size_of::<TableListMessage>() as u32,
+            count: 0,
+            your_table: -1,
+            tables: [TableInfo::default(); common::MAX_TABLES],
+        }
+    }
+    pub fn tables(&self) -> &[TableInfo] {
+        &self.tables[..self.count as usize]
+    }
+}
+
+impl Default for TableListMessage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Reason the server refused a join or spectate message.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum JoinError {
+    MagicNumber,
+    Version,
+    EmptyName,
+    InvalidName,
+    DuplicateName,
+    TableFull,
+    UnknownTable,
+    // A Muon client at a table that isn't for four players.
+    PlayerCount,
+}
+
+impl fmt::Display for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            JoinError::MagicNumber => "Not a Big2 server",
+            JoinError::Version => "Version not supported by the server",
+            JoinError::EmptyName => "Name is empty",
+            JoinError::InvalidName => "Name is invalid",
+            JoinError::DuplicateName => "Name is already taken at this table",
+            JoinError::TableFull => "Table is full",
+            JoinError::UnknownTable => "Table does not exist",
+            JoinError::PlayerCount => "This client only plays with four players",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RejectMessage {
+    pub kind: u32,
+    pub size: u32,
+    pub reason: JoinError,
+}
+
+impl RejectMessage {
+    // On the wire the reason is a u32, in memory it is only one byte.
+    pub const SIZE: usize = 3 * mem::size_of::<u32>();
+}
+
+// The rule set of the server, sent after a join or spectate. See RuleSet::to_bits.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RulesMessage {
+    pub kind: u32,
+    pub size: u32,
+    pub rules: u32,
+}
+
+// Not part of the Muon version. The hand of your seat when it has more than the 13 cards
+// that fit in the StateMessage, sent right before it. See RuleSet::hand_size.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct HandMessage {
+    pub kind: u32,
+    pub size: u32,
+    pub cards: u64,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -54,7 +177,16 @@ pub struct StateMessagePlayer {
     pub delta_score: i32,
     pub is_ready: bool,
     pub has_passed_this_cycle: bool,
-    pub padding: u16,
+    pub flags: u16,
+}
+
+impl StateMessagePlayer {
+    // Flags are not used by the Muon version, it always sends zero.
+    pub const DISCONNECTED: u16 = 0x1;
+
+    pub fn is_disconnected(&self) -> bool {
+        self.flags & StateMessagePlayer::DISCONNECTED != 0
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -70,13 +202,18 @@ pub struct PlayMessage {
     pub cards: muon::InlineList8,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct StateMessageAction {
     pub action_type: StateMessageActionType,
     pub player: i32,
     pub cards: muon::InlineList8,
     pub is_end_of_cycle: bool,
-    pub padding: [u8; 3],
+    // Seconds left for the current turn, 0 is no time limit. Muon always sends 0.
+    pub turn_time: u8,
+    // A DEAL that ended the round, see InstantWin::to_byte. 0 is a normal deal, Muon always
+    // sends 0.
+    pub instant_win: u8,
+    pub padding: [u8; 1],
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -106,6 +243,83 @@ impl StateMessage {
         sm.size = mem::size_of::<StateMessage>() as u32;
         sm
     }
+    // Build the StateMessage as seen from seat `your_index`. Only the hand of that seat is
+    // included, an index outside 0..4 gets no hand at all. A hand of more than 13 cards
+    // doesn't fit and goes in a HandMessage. Names and ready state are not part of the game
+    // state and are left empty.
+    pub fn from_srv_game_state(gs: &big2rules::SrvGameState, your_index: i32) -> Self {
+        let mut sm = StateMessage::new(None);
+
+        sm.kind = 5;
+        sm.round = gs.round as u32;
+        sm.num_rounds = gs.rounds as u32;
+        sm.turn = gs.turn;
+        sm.your_index = your_index;
+        if (0..4).contains(&your_index) {
+            let hand = gs.cards[your_index as usize];
+            if let Ok(your_hand) = muon::InlineList16::try_from(hand) {
+                sm.your_hand = your_hand;
+            }
+        }
+
+        for (p, player) in sm.players.iter_mut().enumerate() {
+            player.score = gs.score[p] as i32;
+            player.delta_score = gs.delta_score[p] as i32;
+            if gs.round != 0 {
+                player.num_cards = gs.card_cnt[p] as i32;
+            }
+            player.has_passed_this_cycle = gs.has_passed & (1 << p) != 0;
+        }
+
+        let (action_type, player, cards, board, is_end_of_cycle) = match gs.action {
+            big2rules::SrvAction::Update => (StateMessageActionType::UPDATE, 0, 0, gs.board, false),
+            big2rules::SrvAction::Deal => (StateMessageActionType::DEAL, gs.turn, 0, 0, false),
+            big2rules::SrvAction::InstantWin { player, win } => {
+                sm.action.instant_win = win.to_byte();
+                (StateMessageActionType::DEAL, player, 0, 0, false)
+            }
+            big2rules::SrvAction::Play {
+                player,
+                hand,
+                board,
+                is_end_of_cycle,
+            } => (
+                StateMessageActionType::PLAY,
+                player,
+                hand,
+                board,
+                is_end_of_cycle,
+            ),
+            big2rules::SrvAction::Pass {
+                player,
+                board,
+                is_end_of_cycle,
+            } => (
+                StateMessageActionType::PASS,
+                player,
+                0,
+                board,
+                is_end_of_cycle,
+            ),
+        };
+        sm.board = muon::InlineList8::try_from(board).unwrap();
+        sm.action.action_type = action_type;
+        sm.action.player = player;
+        sm.action.cards = muon::InlineList8::try_from(cards).unwrap();
+        sm.action.is_end_of_cycle = is_end_of_cycle;
+        sm
+    }
+    // The instant win that ended the round at the deal.
+    pub fn instant_win(&self) -> Option<big2rules::rules::InstantWin> {
+        if self.action.action_type != StateMessageActionType::DEAL {
+            return None;
+        }
+        big2rules::rules::InstantWin::from_byte(self.action.instant_win)
+    }
+    // Spectators don't have a seat and get no hand.
+    pub fn is_spectator(&self) -> bool {
+        !(0..4).contains(&self.your_index)
+    }
     pub fn current_player(&self) -> Option<usize> {
         if self.turn == -1 || self.turn < 0 || self.turn > 3 {
             return None;
@@ -175,8 +389,9 @@ impl StateMessage {
 
 pub mod muon {
     use super::*;
+    use big2rules::cards::Hand;
 
-    #[derive(Serialize, Deserialize, Debug)]
+    #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
     pub struct String16 {
         pub data: [u8; 16],
         pub count: i32,
@@ -211,7 +426,7 @@ pub mod muon {
         }
     }
 
-    #[derive(Serialize, Deserialize, Debug)]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
     pub struct InlineList16 {
         pub data: [u8; 16],
         pub count: i32,
@@ -236,6 +451,30 @@ pub mod muon {
         }
     }
 
+    impl TryFrom<u64> for InlineList16 {
+        type Error = &'static str;
+
+        fn try_from(hand: u64) -> Result<Self, Self::Error> {
+            let mut cards = InlineList16 {
+                data: [0; 16],
+                count: 0,
+            };
+
+            let hand = Hand::try_from(hand).map_err(|_| "Invalid Hand!")?;
+            let num_cards = hand.len();
+            if num_cards > 13 {
+                return Err("Invalid Hand!");
+            }
+
+            cards.count = num_cards as i32;
+
+            for (p, card) in hand.iter().enumerate() {
+                cards.data[p] = cards_to_byte(card.mask());
+            }
+            Ok(cards)
+        }
+    }
+
     impl TryFrom<u64> for InlineList8 {
         type Error = &'static str;
 
@@ -245,22 +484,16 @@ pub mod muon {
                 count: 0,
             };
 
-            let num_cards = hand.count_ones();
-            if num_cards > 6 || num_cards == 4 || hand & 0xFFF != 0 {
+            let hand = Hand::try_from(hand).map_err(|_| "Invalid Hand!")?;
+            let num_cards = hand.len();
+            if num_cards > 6 || num_cards == 4 {
                 return Err("Invalid Hand!");
             }
 
             cards.count = num_cards as i32;
 
-            let mut hand = hand;
-            let mut p: usize = 0;
-            while hand != 0 {
-                let zeros = hand.trailing_zeros() as u64;
-
-                let mask = 1 << zeros;
-                hand ^= mask;
-                cards.data[p] = cards_to_byte(mask);
-                p += 1;
+            for (p, card) in hand.iter().enumerate() {
+                cards.data[p] = cards_to_byte(card.mask());
             }
             Ok(cards)
         }
@@ -308,10 +541,21 @@ pub mod muon {
 }
 
 pub mod common {
+    use std::time::Duration;
+
     pub const PORT: u16 = 27191;
     pub const VERSION: u32 = 6;
     pub const MAGICNUMBER: u32 = 0x3267_6962;
     pub const BUFSIZE: usize = 4096;
+    pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+    pub const PEER_TIMEOUT: Duration = Duration::from_secs(10);
+    pub const SRV_TICK: Duration = Duration::from_millis(100);
+    pub const RECONNECT_ATTEMPTS: u32 = 6;
+    pub const RECONNECT_DELAY: Duration = Duration::from_millis(250);
+    pub const RECONNECT_DELAY_MAX: Duration = Duration::from_secs(4);
+    pub const MAX_TABLES: usize = 16;
+    // Muon clients always join this table.
+    pub const DEFAULT_TABLE: u32 = 0;
 }
 
 pub mod client {
@@ -321,18 +565,78 @@ pub mod client {
         id: Option<thread::JoinHandle<()>>,
         rx: Receiver<Vec<u8>>,
         tx: Sender<Vec<u8>>,
+        session: u32,
+        table_list: Option<TableListMessage>,
+        rules: Option<big2rules::rules::RuleSet>,
+        hand: Option<u64>,
+        // The server answered a table list, so it knows the join with session and table.
+        lobby: bool,
+    }
+
+    fn thread_tcp(
+        remote_addr: String,
+        mut ts: TcpStream,
+        tx: Sender<Vec<u8>>,
+        rx: Receiver<Vec<u8>>,
+    ) {
+        let mut join = Vec::<u8>::new();
+
+        while tcp_session(&mut ts, &tx, &rx, &mut join) {
+            // Only try to get back to the table when we did join one.
+            if join.is_empty() {
+                break;
+            }
+            match reconnect(&remote_addr, &join) {
+                None => {
+                    error!("TCP: Unable to reconnect!");
+                    break;
+                }
+                Some(s) => ts = s,
+            }
+        }
+    }
+
+    fn reconnect(remote_addr: &str, join: &[u8]) -> Option<TcpStream> {
+        let mut delay = common::RECONNECT_DELAY;
+
+        for attempt in 1..=common::RECONNECT_ATTEMPTS {
+            thread::sleep(delay);
+            info!("TCP: Reconnect attempt {} to {}", attempt, remote_addr);
+            if let Ok(mut ts) = connect_stream(remote_addr) {
+                if ts.write_all(join).is_ok() {
+                    return Some(ts);
+                }
+            }
+            delay = std::cmp::min(delay * 2, common::RECONNECT_DELAY_MAX);
+        }
+        None
     }
 
-    fn thread_tcp(mut ts: TcpStream, tx: Sender<Vec<u8>>, rx: Receiver<Vec<u8>>) {
+    // Returns true when the connection is lost and false when the client stopped.
+    fn tcp_session(
+        ts: &mut TcpStream,
+        tx: &Sender<Vec<u8>>,
+        rx: &Receiver<Vec<u8>>,
+        join: &mut Vec<u8>,
+    ) -> bool {
         let mut buffer = [0; common::BUFSIZE];
+        let mut heartbeat = Instant::now();
 
         'tcp_loop: loop {
+            if heartbeat.elapsed() >= common::HEARTBEAT_INTERVAL {
+                heartbeat = Instant::now();
+                let hb = bincode::serialize(&Message::new(6)).unwrap();
+                if let Err(e) = ts.write(&hb) {
+                    error!("TCP: Error write heartbeat. {}", e);
+                }
+            }
+
             let tx_data = rx.try_recv();
             match tx_data {
                 Err(std::sync::mpsc::TryRecvError::Empty) => (),
                 Err(std::sync::mpsc::TryRecvError::Disconnected) => {
                     error!("TCP: TX channel disconnected");
-                    break;
+                    return false;
                 }
                 Ok(data) => {
                     trace!("TCP: PUSH: {:x?}", data);
@@ -340,6 +644,10 @@ pub mod client {
                     if let Err(e) = ret {
                         error!("TCP: Error write. {}", e);
                     }
+                    // Remember the join or spectate message to send it again after a reconnect.
+                    if data[0] == 1 || data[0] == 7 {
+                        *join = data;
+                    }
                 }
             }
 
@@ -354,7 +662,7 @@ pub mod client {
                     continue;
                 }
                 error!("TCP: RX error {:?}", e);
-                break;
+                return true;
             }
 
             let mut n_bytes = ret.unwrap();
@@ -363,7 +671,7 @@ pub mod client {
 
             if n_bytes == 0 {
                 info!("Connection Closed!");
-                break;
+                return true;
             }
 
             let mut pos: usize = 0;
@@ -377,6 +685,69 @@ pub mod client {
                 let dm: client::DetectMessage =
                     bincode::deserialize(&buffer[pos..].to_vec()).unwrap();
 
+                // Lobby
+                const TL_SIZE: usize = mem::size_of::<TableListMessage>();
+                if dm.kind == 9 && dm.size as usize == TL_SIZE {
+                    if n_bytes < TL_SIZE {
+                        continue 'tcp_loop;
+                    }
+
+                    if let Err(e) = tx.send(buffer[pos..pos + TL_SIZE].to_vec()) {
+                        error!("TCP: MPSC TX ERROR {:?}", e);
+                        return false;
+                    }
+                    pos += TL_SIZE;
+                    n_bytes -= TL_SIZE;
+                    continue;
+                }
+
+                // Rejected, the server closes the connection. Don't reconnect.
+                const RM_SIZE: usize = RejectMessage::SIZE;
+                if dm.kind == 11 && dm.size as usize == RM_SIZE {
+                    if n_bytes < RM_SIZE {
+                        continue 'tcp_loop;
+                    }
+
+                    join.clear();
+                    if let Err(e) = tx.send(buffer[pos..pos + RM_SIZE].to_vec()) {
+                        error!("TCP: MPSC TX ERROR {:?}", e);
+                        return false;
+                    }
+                    pos += RM_SIZE;
+                    n_bytes -= RM_SIZE;
+                    continue;
+                }
+
+                const HM_SIZE: usize = mem::size_of::<HandMessage>();
+                if dm.kind == 13 && dm.size as usize == HM_SIZE {
+                    if n_bytes < HM_SIZE {
+                        continue 'tcp_loop;
+                    }
+
+                    if let Err(e) = tx.send(buffer[pos..pos + HM_SIZE].to_vec()) {
+                        error!("TCP: MPSC TX ERROR {:?}", e);
+                        return false;
+                    }
+                    pos += HM_SIZE;
+                    n_bytes -= HM_SIZE;
+                    continue;
+                }
+
+                const RLM_SIZE: usize = mem::size_of::<RulesMessage>();
+                if dm.kind == 12 && dm.size as usize == RLM_SIZE {
+                    if n_bytes < RLM_SIZE {
+                        continue 'tcp_loop;
+                    }
+
+                    if let Err(e) = tx.send(buffer[pos..pos + RLM_SIZE].to_vec()) {
+                        error!("TCP: MPSC TX ERROR {:?}", e);
+                        return false;
+                    }
+                    pos += RLM_SIZE;
+                    n_bytes -= RLM_SIZE;
+                    continue;
+                }
+
                 // Update
                 const SM_SIZE: usize = mem::size_of::<StateMessage>();
                 if dm.kind == 5 && dm.size as usize == SM_SIZE {
@@ -390,7 +761,7 @@ pub mod client {
                     let ret = tx.send(buf);
                     if ret.is_err() {
                         error!("TCP: MPSC TX ERROR {:?}", ret.unwrap_err());
-                        break;
+                        return false;
                     }
                     pos += SM_SIZE;
                     n_bytes -= SM_SIZE;
@@ -422,6 +793,30 @@ pub mod client {
         }
     }
 
+    fn connect_stream(remote_addr: &str) -> Result<TcpStream, io::Error> {
+        let server_list = remote_addr.to_socket_addrs();
+        if let Err(_e) = server_list {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "DNS Name not found!",
+            ));
+        }
+
+        for l in server_list.unwrap() {
+            info!("Connecting to {:?}", l);
+            if let Ok(s) = TcpStream::connect_timeout(&l, Duration::from_secs(1)) {
+                s.set_read_timeout(Some(Duration::from_millis(10)))?;
+                info!("Connected to {:?}!", s.peer_addr());
+                return Ok(s);
+            }
+        }
+        info!("Unable to connect!");
+        Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "Can't Connect Timeout!",
+        ))
+    }
+
     pub fn disconnect(mut tc: TcpClient) {
         debug!("Shutdown tcp thread!");
         drop(tc.tx);
@@ -432,55 +827,35 @@ pub mod client {
 
     impl TcpClient {
         pub fn connect(remote_addr: String) -> Result<TcpClient, io::Error> {
-            let server_list = remote_addr.to_socket_addrs();
-            if let Err(_e) = server_list {
-                return Err(io::Error::new(
-                    io::ErrorKind::NotFound,
-                    "DNS Name not found!",
-                ));
-            }
-            let mut servers = server_list.unwrap();
-
-            loop {
-                let server = servers.next();
-                if server.is_none() {
-                    break;
-                }
-                let l = server.unwrap();
-                info!("Connecting to {:?}", l);
-                let ret = TcpStream::connect_timeout(&l, Duration::from_secs(1));
-                match ret {
-                    Err(_) => continue,
-                    Ok(s) => {
-                        s.set_read_timeout(Some(Duration::from_millis(10)))?;
-                        let (tx, rx) = std::sync::mpsc::channel();
-                        let (tx1, rx1) = std::sync::mpsc::channel();
-
-                        info!("Connected to {:?}!", s.peer_addr());
-
-                        let tcp_thread = thread::Builder::new().name("big2_tcp".into());
-                        let id = tcp_thread.spawn(move || {
-                            thread_tcp(s, tx, rx1);
-                        })?;
+            // The session lets the server give our seat back after a reconnect.
+            TcpClient::connect_session(remote_addr, rand::random::<u32>() | 1)
+        }
 
-                        // if let Err(e) = id {
-                        //     println!("Can't create thread {}!", e);
-                        //     return Err(e);
-                        // }
+        // Connect with the session of an earlier client to take back its seat.
+        pub fn connect_session(remote_addr: String, session: u32) -> Result<TcpClient, io::Error> {
+            let s = connect_stream(&remote_addr)?;
+            let (tx, rx) = std::sync::mpsc::channel();
+            let (tx1, rx1) = std::sync::mpsc::channel();
+
+            let tcp_thread = thread::Builder::new().name("big2_tcp".into());
+            let id = tcp_thread.spawn(move || {
+                thread_tcp(remote_addr, s, tx, rx1);
+            })?;
+
+            Ok(TcpClient {
+                rx: rx,
+                tx: tx1,
+                id: Some(id),
+                session,
+                table_list: None,
+                rules: None,
+                hand: None,
+                lobby: false,
+            })
+        }
 
-                        return Ok(TcpClient {
-                            rx: rx,
-                            tx: tx1,
-                            id: Some(id),
-                        });
-                    }
-                }
-            }
-            info!("Unable to connect!");
-            Err(io::Error::new(
-                io::ErrorKind::TimedOut,
-                "Can't Connect Timeout!",
-            ))
+        pub fn session(&self) -> u32 {
+            self.session
         }
 
         pub fn action_pass(&mut self) -> Result<usize, io::Error> {
@@ -520,16 +895,33 @@ pub mod client {
         }
 
         pub fn send_join_msg(&mut self, name: &String) -> Result<usize, io::Error> {
+            self.send_join_table_msg(name, common::DEFAULT_TABLE)
+        }
+
+        pub fn send_join_table_msg(
+            &mut self,
+            name: &String,
+            table: u32,
+        ) -> Result<usize, io::Error> {
+            // A Muon server only knows the join without session and table.
+            let size = if self.lobby {
+                mem::size_of::<JoinMessage>()
+            } else {
+                JoinMessage::MUON_SIZE
+            };
             let jm = JoinMessage {
                 kind: 1,
-                size: mem::size_of::<JoinMessage>() as u32,
+                size: size as u32,
                 magicnumber: common::MAGICNUMBER,
                 version: common::VERSION,
                 name: muon::String16::from_string(&name),
+                session: self.session,
+                table,
             };
 
             // Send Join Message.
-            let jmb = bincode::serialize(&jm).unwrap();
+            let mut jmb = bincode::serialize(&jm).unwrap();
+            jmb.truncate(size);
             let ret = self.tx.send(jmb);
             if ret.is_err() {
                 return Err(io::Error::new(io::ErrorKind::BrokenPipe, "Thread died!"));
@@ -537,6 +929,74 @@ pub mod client {
             Ok(0)
         }
 
+        // Watch the table without taking a seat.
+        pub fn send_spectate_msg(&mut self, table: u32) -> Result<usize, io::Error> {
+            let sm = SpectateMessage {
+                kind: 7,
+                size: mem::size_of::<SpectateMessage>() as u32,
+                table,
+            };
+            let byte_buf = bincode::serialize(&sm).unwrap();
+            let ret = self.tx.send(byte_buf);
+            if ret.is_err() {
+                return Err(io::Error::new(io::ErrorKind::BrokenPipe, "Thread died!"));
+            }
+            Ok(0)
+        }
+
+        // The server answers with a TableListMessage, see `table_list`.
+        pub fn send_list_tables_msg(&mut self) -> Result<usize, io::Error> {
+            let sm = Message::new(8);
+            let byte_buf = bincode::serialize(&sm).unwrap();
+            let ret = self.tx.send(byte_buf);
+            if ret.is_err() {
+                return Err(io::Error::new(io::ErrorKind::BrokenPipe, "Thread died!"));
+            }
+            Ok(0)
+        }
+
+        // Ask for the table list to find out if the server has a lobby, the Muon version
+        // doesn't answer. Until then `send_join_table_msg` sends the Muon join.
+        pub fn detect_lobby(&mut self, timeout: Duration) -> Result<bool, io::Error> {
+            self.send_list_tables_msg()?;
+            let start = Instant::now();
+            while !self.lobby && start.elapsed() < timeout {
+                self.check_buffer()?;
+                thread::sleep(Duration::from_millis(10));
+            }
+            Ok(self.lobby)
+        }
+
+        pub fn send_create_table_msg(&mut self, rounds: u8) -> Result<usize, io::Error> {
+            let sm = CreateTableMessage {
+                kind: 10,
+                size: mem::size_of::<CreateTableMessage>() as u32,
+                rounds: rounds as u32,
+            };
+            let byte_buf = bincode::serialize(&sm).unwrap();
+            let ret = self.tx.send(byte_buf);
+            if ret.is_err() {
+                return Err(io::Error::new(io::ErrorKind::BrokenPipe, "Thread died!"));
+            }
+            Ok(0)
+        }
+
+        // The last table list received by `check_buffer`.
+        pub fn table_list(&mut self) -> Option<TableListMessage> {
+            self.table_list.take()
+        }
+
+        // The rules announced by the server, None until the join or spectate is accepted.
+        pub fn rules(&self) -> Option<big2rules::rules::RuleSet> {
+            self.rules
+        }
+
+        // The hand of the last HandMessage. Take it with the StateMessage that follows it,
+        // that StateMessage has no hand.
+        pub fn take_hand(&mut self) -> Option<u64> {
+            self.hand.take()
+        }
+
         pub fn check_buffer(&mut self) -> Result<Option<StateMessage>, io::Error> {
             let buffer = self.rx.try_recv();
 
@@ -562,7 +1022,38 @@ pub mod client {
                         return Ok(Some(StateMessage::new(Some(&buffer))));
                     }
 
-                    if dm.kind > 6 || dm.size as usize > bytes {
+                    if dm.kind == 9 && dm.size as usize == mem::size_of::<TableListMessage>() {
+                        self.table_list = bincode::deserialize(&buffer).ok();
+                        self.lobby |= self.table_list.is_some();
+                        return Ok(None);
+                    }
+
+                    if dm.kind == 11 && dm.size as usize == RejectMessage::SIZE {
+                        let rm: RejectMessage = bincode::deserialize(&buffer).map_err(|e| {
+                            io::Error::new(
+                                io::ErrorKind::InvalidData,
+                                format!("Invalid RejectMessage: {}", e),
+                            )
+                        })?;
+                        return Err(io::Error::new(
+                            io::ErrorKind::ConnectionRefused,
+                            format!("Join rejected: {}!", rm.reason),
+                        ));
+                    }
+
+                    if dm.kind == 12 && dm.size as usize == mem::size_of::<RulesMessage>() {
+                        let rm: RulesMessage = bincode::deserialize(&buffer).unwrap();
+                        self.rules = big2rules::rules::RuleSet::from_bits(rm.rules);
+                        return Ok(None);
+                    }
+
+                    if dm.kind == 13 && dm.size as usize == mem::size_of::<HandMessage>() {
+                        let hm: HandMessage = bincode::deserialize(&buffer).unwrap();
+                        self.hand = Some(hm.cards);
+                        return Ok(None);
+                    }
+
+                    if dm.kind > 13 || dm.size as usize > bytes {
                         error!("Unknown packet drop {}", bytes);
                     }
 
@@ -573,49 +1064,1075 @@ pub mod client {
     }
 }
 
-#[cfg(test)]
-mod tests {
+pub mod server {
     use super::*;
+    use big2rules::bot::Bot;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::net::{Shutdown, TcpListener};
+    use std::sync::mpsc::{self, RecvTimeoutError};
+
+    enum PeerEvent {
+        Connected(usize, TcpStream),
+        Join(usize, JoinMessage),
+        Play(usize, u64),
+        Pass(usize),
+        Ready(usize),
+        Heartbeat(usize),
+        Spectate(usize, u32),
+        ListTables(usize),
+        CreateTable(usize, u8),
+        Closed(usize),
+    }
 
-    // #[test]
-    // fn a_connect() {
-    //  let name = String::from("René");
-    //  let JM: JoinMessage = client::joinMessage(&name);
-    //  let JMB = bincode::serialize(&JM).unwrap();
-    //  println!("{:x?}", JMB);
-    //  //                       12356789T123456
-    //  let name = String::from("René to long to5123123");
-    //  let JM: JoinMessage = client::joinMessage(&name);
-    //  let JMB = bincode::serialize(&JM).unwrap();
-    //  println!("{:x?}", JMB);
-    // }
-    /*  #[test]
-    fn b_connect() {
-        let sm_size = std::mem::size_of::<StateMessage>();
-        let eb = &[0u8; std::mem::size_of::<StateMessage>()];
-        let mut sm: StateMessage = bincode::deserialize(eb).unwrap();
-        sm.size = sm_size as u32;
-        sm.action = StateMessageActionType::play;
+    struct Peer {
+        id: usize,
+        ts: TcpStream,
+        last_seen: Instant,
+        // Table the peer is seated at or watching.
+        table: Option<u32>,
+        is_spectator: bool,
+    }
 
-        let smb = bincode::serialize(&sm).unwrap();
-        println!("{:x?}", smb);
-    } */
-    #[test]
-    fn c_statemessage_respone() {
-        let buffer: &[u8] = &[
-            5, 0, 0, 0, 0xe0, 0, 0, 0, 1, 0, 0, 0, 8, 0, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 0x15, 7,
-            0x37, 0x28, 0x38, 0x39, 0xa, 0x2b, 0x3b, 0x2c, 0x1d, 0x3d, 2, 0, 0, 0, 0xd, 0, 0, 0,
-            0x54, 0x69, 0x6b, 0x6b, 0x69, 0x65, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 6, 0, 0, 0, 0, 0, 0,
-            0, 9, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0x68, 0x6f, 0x73, 0x74, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 4, 0, 0, 0, 0, 0, 0, 0, 7, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0x52, 0x65,
-            0x6e, 0x65, 0x31, 0x32, 0x33, 0, 0, 0, 0, 0, 0, 0, 0, 0, 7, 0, 0, 0, 0, 0, 0, 0, 0xb,
-            0, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 0x52, 0x65, 0x6e, 0x65, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0xd, 0, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 0x16, 0x26, 0, 0,
-            0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0,
-        ];
+    struct Seat {
+        name: muon::String16,
+        session: u32,
+        is_ready: bool,
+        peer: Option<usize>,
+        // Made when the bot takes the seat, it keeps its state for the rest of the match.
+        bot: Option<Box<dyn Bot>>,
+    }
 
-        let sm = StateMessage::new(Some(buffer));
+    impl Seat {
+        fn empty() -> Self {
+            Seat {
+                name: muon::String16 {
+                    data: [0; 16],
+                    count: 0,
+                },
+                session: 0,
+                is_ready: false,
+                peer: None,
+                bot: None,
+            }
+        }
+        fn is_empty(&self) -> bool {
+            self.name.count == 0
+        }
+        // A player that lost the connection.
+        fn is_disconnected(&self) -> bool {
+            !self.is_empty() && self.peer.is_none() && self.bot.is_none()
+        }
+    }
+
+    struct Table {
+        id: u32,
+        gs: big2rules::SrvGameState,
+        // One for every player of the rules.
+        seats: Vec<Seat>,
+        created: Instant,
+        turn_start: Instant,
+        // Since when nobody at the table is connected, during a match.
+        abandoned: Option<Instant>,
+    }
+
+    impl Table {
+        fn new(id: u32, rounds: u8, rules: big2rules::rules::RuleSet) -> Self {
+            Table {
+                id,
+                gs: big2rules::SrvGameState::with_rules(rounds, rules),
+                seats: (0..rules.players).map(|_| Seat::empty()).collect(),
+                created: Instant::now(),
+                turn_start: Instant::now(),
+                abandoned: None,
+            }
+        }
+
+        // Restart the turn timer after every deal, play and pass. Also when the same player
+        // stays on turn, like after playing the highest card.
+        fn start_turn(&mut self) {
+            self.turn_start = Instant::now();
+        }
+
+        fn turn_time_left(&self, turn_time: u8) -> Option<Duration> {
+            if turn_time == 0 || self.gs.turn < 0 {
+                return None;
+            }
+            let limit = Duration::from_secs(turn_time as u64);
+            Some(
+                limit
+                    .checked_sub(self.turn_start.elapsed())
+                    .unwrap_or_default(),
+            )
+        }
+
+        fn reset(&mut self) {
+            self.gs = big2rules::SrvGameState::with_rules(self.gs.rounds, self.gs.rules);
+            self.seats = (0..self.gs.rules.players).map(|_| Seat::empty()).collect();
+            self.abandoned = None;
+        }
+
+        // A player is back at an abandoned table, give them a full turn.
+        fn resume(&mut self) {
+            if self.abandoned.take().is_some() {
+                self.start_turn();
+            }
+        }
+
+        fn is_between_matches(&self) -> bool {
+            use big2rules::Phase;
+            matches!(self.gs.phase, Phase::WaitingForReady | Phase::MatchOver)
+        }
+
+        fn is_between_rounds(&self) -> bool {
+            self.is_between_matches() || self.gs.phase == big2rules::Phase::RoundOver
+        }
+
+        fn seat_of(&self, id: usize) -> Option<usize> {
+            self.seats.iter().position(|s| s.peer == Some(id))
+        }
+
+        // No player has a seat at this table, bots don't count.
+        fn is_idle(&self) -> bool {
+            self.seats.iter().all(|s| s.is_empty() || s.bot.is_some())
+        }
+
+        // None of the players is connected.
+        fn is_unattended(&self) -> bool {
+            self.seats.iter().all(|s| s.peer.is_none())
+        }
+
+        fn state_message(&self, seat: Option<usize>, turn_time: u8) -> StateMessage {
+            let your_index = seat.map_or(-1, |s| s as i32);
+            let mut sm = StateMessage::from_srv_game_state(&self.gs, your_index);
+
+            if let Some(left) = self.turn_time_left(turn_time) {
+                // Round up, 0 means no time limit. Not with div_ceil, it needs Rust 1.73.
+                #[allow(clippy::manual_div_ceil)]
+                let secs = ((left.as_millis() + 999) / 1000).max(1);
+                sm.action.turn_time = secs as u8;
+            }
+
+            for (player, seat) in sm.players.iter_mut().zip(self.seats.iter()) {
+                player.name = seat.name;
+                player.is_ready = seat.is_ready;
+                if seat.is_disconnected() {
+                    player.flags |= StateMessagePlayer::DISCONNECTED;
+                }
+            }
+            sm
+        }
+    }
+
+    pub struct GameServer {
+        tables: Vec<Table>,
+        next_table: u32,
+        peers: Vec<Peer>,
+        rx: Receiver<PeerEvent>,
+        matches: u32,
+        turn_time: u8,
+        bots: Option<big2rules::bot::Strategy>,
+        bot_name: String,
+        rules: big2rules::rules::RuleSet,
+        seed: Option<u64>,
+        matches_played: u32,
+        is_shutdown: bool,
+    }
+
+    fn thread_listen(listener: TcpListener, tx: Sender<PeerEvent>) {
+        let mut id: usize = 0;
+
+        for stream in listener.incoming() {
+            let ts = match stream {
+                Err(e) => {
+                    error!("SRV: Accept error {}", e);
+                    continue;
+                }
+                Ok(ts) => ts,
+            };
+            info!("SRV: Connection from {:?}", ts.peer_addr());
+
+            let rs = match ts.try_clone() {
+                Err(e) => {
+                    error!("SRV: Can't clone stream {}", e);
+                    continue;
+                }
+                Ok(rs) => rs,
+            };
+
+            id += 1;
+            if tx.send(PeerEvent::Connected(id, ts)).is_err() {
+                break;
+            }
+
+            let tx = tx.clone();
+            let peer_thread = thread::Builder::new().name(format!("big2_peer{}", id));
+            if let Err(e) = peer_thread.spawn(move || thread_peer(rs, id, tx)) {
+                error!("SRV: Can't create peer thread {}", e);
+            }
+        }
+    }
+
+    fn thread_peer(mut ts: TcpStream, id: usize, tx: Sender<PeerEvent>) {
+        let mut buffer = [0; common::BUFSIZE];
+        let mut data = Vec::<u8>::with_capacity(common::BUFSIZE);
+
+        'peer_loop: loop {
+            let n_bytes = match ts.read(&mut buffer) {
+                Err(e) => {
+                    info!("SRV: P{} RX error {:?}", id, e);
+                    break;
+                }
+                Ok(0) => {
+                    info!("SRV: P{} Connection Closed!", id);
+                    break;
+                }
+                Ok(n) => n,
+            };
+            data.extend_from_slice(&buffer[..n_bytes]);
+
+            const DM_SIZE: usize = mem::size_of::<DetectMessage>();
+            while data.len() >= DM_SIZE {
+                let dm: DetectMessage = bincode::deserialize(&data[..DM_SIZE]).unwrap();
+                let size = dm.size as usize;
+
+                if !(DM_SIZE..=common::BUFSIZE).contains(&size) {
+                    error!(
+                        "SRV: P{} Invalid packet! Kind {} Size {}",
+                        id, dm.kind, size
+                    );
+                    break 'peer_loop;
+                }
+                if data.len() < size {
+                    continue 'peer_loop;
+                }
+
+                let packet: Vec<u8> = data.drain(..size).collect();
+                let event = match parse_packet(id, dm.kind, &packet) {
+                    None => {
+                        error!("SRV: P{} Unknown packet {:x?}", id, packet);
+                        break 'peer_loop;
+                    }
+                    Some(event) => event,
+                };
+                if tx.send(event).is_err() {
+                    return;
+                }
+            }
+        }
+        let _ = tx.send(PeerEvent::Closed(id));
+    }
+
+    // Returns None on a malformed packet.
+    fn parse_packet(id: usize, kind: u32, packet: &[u8]) -> Option<PeerEvent> {
+        const JM_SIZE: usize = mem::size_of::<JoinMessage>();
+        const MUON_JM_SIZE: usize = JoinMessage::MUON_SIZE;
+        const PM_SIZE: usize = mem::size_of::<PlayMessage>();
+        const M_SIZE: usize = mem::size_of::<Message>();
+        const SPM_SIZE: usize = mem::size_of::<SpectateMessage>();
+        const CTM_SIZE: usize = mem::size_of::<CreateTableMessage>();
+
+        match (kind, packet.len()) {
+            (1, JM_SIZE) => {
+                let jm: JoinMessage = bincode::deserialize(packet).ok()?;
+                Some(PeerEvent::Join(id, jm))
+            }
+            // Muon JoinMessage, without session and table.
+            (1, MUON_JM_SIZE) => {
+                let mut packet = packet.to_vec();
+                packet.resize(JM_SIZE, 0);
+                let mut jm: JoinMessage = bincode::deserialize(&packet).ok()?;
+                jm.table = common::DEFAULT_TABLE;
+                Some(PeerEvent::Join(id, jm))
+            }
+            (2, PM_SIZE) => {
+                let pm: PlayMessage = bincode::deserialize(packet).ok()?;
+                let cards = pm.cards.into_card().ok()?;
+                Some(PeerEvent::Play(id, cards))
+            }
+            (3, M_SIZE) => Some(PeerEvent::Pass(id)),
+            (4, M_SIZE) => Some(PeerEvent::Ready(id)),
+            // HeartbeatMessage
+            (6, M_SIZE) => Some(PeerEvent::Heartbeat(id)),
+            // Lobby messages, not part of the Muon version.
+            (7, SPM_SIZE) => {
+                let spm: SpectateMessage = bincode::deserialize(packet).ok()?;
+                Some(PeerEvent::Spectate(id, spm.table))
+            }
+            (8, M_SIZE) => Some(PeerEvent::ListTables(id)),
+            (10, CTM_SIZE) => {
+                let ctm: CreateTableMessage = bincode::deserialize(packet).ok()?;
+                let rounds = u8::try_from(ctm.rounds).ok()?;
+                Some(PeerEvent::CreateTable(id, rounds))
+            }
+            _ => None,
+        }
+    }
+
+    impl GameServer {
+        // Host `matches` matches of `rounds` rounds, `matches` 0 means keep hosting new matches.
+        // The default table is always there, players can open more tables from the lobby.
+        pub fn bind(port: u16, rounds: u8, matches: u32) -> Result<GameServer, io::Error> {
+            let listener = TcpListener::bind(("0.0.0.0", port))?;
+            info!("SRV: Listening on {:?}", listener.local_addr());
+
+            let (tx, rx) = mpsc::channel();
+            let listen_thread = thread::Builder::new().name("big2_listen".into());
+            listen_thread.spawn(move || {
+                thread_listen(listener, tx);
+            })?;
+
+            Ok(GameServer {
+                tables: vec![Table::new(
+                    common::DEFAULT_TABLE,
+                    rounds,
+                    big2rules::rules::RuleSet::default(),
+                )],
+                next_table: common::DEFAULT_TABLE + 1,
+                peers: Vec::with_capacity(4),
+                rx,
+                matches,
+                turn_time: 0,
+                bots: None,
+                bot_name: String::from("Bot"),
+                rules: big2rules::rules::RuleSet::default(),
+                seed: None,
+                matches_played: 0,
+                is_shutdown: false,
+            })
+        }
+
+        // Seconds a player gets for a turn before the server moves for them, 0 is no limit.
+        pub fn set_turn_time(&mut self, turn_time: u8) {
+            self.turn_time = turn_time;
+        }
+
+        // Fill the empty seats with bots when the players at a table are ready. The bots are
+        // called `name` followed by the seat number.
+        pub fn set_bots(&mut self, strategy: Option<big2rules::bot::Strategy>, name: &str) {
+            self.bots = strategy;
+            self.bot_name = name.to_string();
+        }
+
+        // The rules of all tables, the players get them when they join. Set them before the
+        // players join, the number of seats follows the rules.
+        pub fn set_rules(&mut self, rules: big2rules::rules::RuleSet) {
+            self.rules = rules;
+            for table in self.tables.iter_mut() {
+                table.gs.rules = rules;
+                table.seats.resize_with(rules.players as usize, Seat::empty);
+            }
+        }
+
+        // Deal all tables from `seed`, so a logged seed replays the same game. None is a
+        // random seed for every table.
+        pub fn set_seed(&mut self, seed: Option<u64>) {
+            self.seed = seed;
+            if let Some(seed) = seed {
+                for table in self.tables.iter_mut() {
+                    table.gs.set_seed(seed);
+                }
+            }
+        }
+
+        pub fn spawn(mut self) -> Result<thread::JoinHandle<()>, io::Error> {
+            let srv_thread = thread::Builder::new().name("big2_srv".into());
+            srv_thread.spawn(move || {
+                self.run();
+            })
+        }
+
+        pub fn run(&mut self) {
+            let mut heartbeat = Instant::now();
+
+            while !self.is_shutdown {
+                match self.rx.recv_timeout(common::SRV_TICK) {
+                    Ok(event) => self.handle_event(event),
+                    Err(RecvTimeoutError::Timeout) => (),
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+                if heartbeat.elapsed() >= common::HEARTBEAT_INTERVAL {
+                    heartbeat = Instant::now();
+                    self.heartbeat();
+                    self.reset_abandoned_tables();
+                    self.remove_idle_tables();
+                }
+                self.check_turn_time();
+            }
+            for peer in self.peers.iter() {
+                let _ = peer.ts.shutdown(Shutdown::Both);
+            }
+            info!("SRV: Stopped");
+        }
+
+        fn handle_event(&mut self, event: PeerEvent) {
+            match event {
+                PeerEvent::Connected(id, ts) => {
+                    self.peers.push(Peer {
+                        id,
+                        ts,
+                        last_seen: Instant::now(),
+                        table: None,
+                        is_spectator: false,
+                    });
+                }
+                PeerEvent::Join(id, jm) => {
+                    self.seen(id);
+                    self.join(id, jm);
+                }
+                PeerEvent::Ready(id) => {
+                    self.seen(id);
+                    self.ready(id);
+                }
+                PeerEvent::Play(id, cards) => {
+                    self.seen(id);
+                    self.play(id, cards);
+                }
+                PeerEvent::Pass(id) => {
+                    self.seen(id);
+                    self.pass(id);
+                }
+                PeerEvent::Heartbeat(id) => self.seen(id),
+                PeerEvent::Spectate(id, table) => {
+                    self.seen(id);
+                    self.spectate(id, table);
+                }
+                PeerEvent::ListTables(id) => {
+                    self.seen(id);
+                    self.send_table_list(id, -1);
+                }
+                PeerEvent::CreateTable(id, rounds) => {
+                    self.seen(id);
+                    self.create_table(id, rounds);
+                }
+                PeerEvent::Closed(id) => self.close(id),
+            }
+            for t in 0..self.tables.len() {
+                self.play_for_absent(t);
+            }
+        }
+
+        fn seen(&mut self, id: usize) {
+            if let Some(peer) = self.peers.iter_mut().find(|p| p.id == id) {
+                peer.last_seen = Instant::now();
+            }
+        }
+
+        fn heartbeat(&mut self) {
+            let hb = bincode::serialize(&Message::new(6)).unwrap();
+            let ids: Vec<usize> = self.peers.iter().map(|p| p.id).collect();
+            for id in ids {
+                self.send(id, &hb);
+            }
+
+            for peer in self.peers.iter() {
+                if peer.last_seen.elapsed() > common::PEER_TIMEOUT {
+                    info!(
+                        "SRV: P{} Timeout, no data for {:?}",
+                        peer.id,
+                        peer.last_seen.elapsed()
+                    );
+                    let _ = peer.ts.shutdown(Shutdown::Both);
+                }
+            }
+        }
+
+        fn table_idx(&self, table: u32) -> Option<usize> {
+            self.tables.iter().position(|t| t.id == table)
+        }
+
+        // Table index and seat of a peer.
+        fn seat_of(&self, id: usize) -> Option<(usize, usize)> {
+            self.tables
+                .iter()
+                .enumerate()
+                .find_map(|(t, table)| table.seat_of(id).map(|seat| (t, seat)))
+        }
+
+        fn num_spectators(&self, table: u32) -> usize {
+            self.peers
+                .iter()
+                .filter(|p| p.is_spectator && p.table == Some(table))
+                .count()
+        }
+
+        fn send_table_list(&mut self, id: usize, your_table: i32) {
+            let mut tlm = TableListMessage::new();
+            tlm.your_table = your_table;
+            for (info, table) in tlm.tables.iter_mut().zip(self.tables.iter()) {
+                *info = TableInfo {
+                    id: table.id,
+                    round: table.gs.round as u32,
+                    num_rounds: table.gs.rounds as u32,
+                    num_players: table.seats.iter().filter(|s| !s.is_empty()).count() as u32,
+                    num_seats: table.seats.len() as u32,
+                    num_spectators: self.num_spectators(table.id) as u32,
+                };
+            }
+            tlm.count = self.tables.len() as u32;
+            let buf = bincode::serialize(&tlm).unwrap();
+            self.send(id, &buf);
+        }
+
+        fn create_table(&mut self, id: usize, rounds: u8) {
+            if rounds == 0 || self.tables.len() >= common::MAX_TABLES {
+                info!("SRV: P{} Can't create table with {} rounds", id, rounds);
+                self.send_table_list(id, -1);
+                return;
+            }
+            let table = self.next_table;
+            self.next_table += 1;
+            info!("SRV: P{} created table {} rounds {}", id, table, rounds);
+            let mut new_table = Table::new(table, rounds, self.rules);
+            if let Some(seed) = self.seed {
+                new_table.gs.set_seed(seed);
+            }
+            self.tables.push(new_table);
+            self.send_table_list(id, table as i32);
+        }
+
+        // Tables, except the default table, are closed when there is nobody left.
+        fn remove_idle_tables(&mut self) {
+            let ids: Vec<u32> = self
+                .tables
+                .iter()
+                .filter(|t| t.id != common::DEFAULT_TABLE)
+                .filter(|t| t.is_idle() && t.created.elapsed() > common::PEER_TIMEOUT)
+                .map(|t| t.id)
+                .filter(|&id| self.num_spectators(id) == 0)
+                .collect();
+            for id in ids {
+                info!("SRV: Close table {}", id);
+                self.tables.retain(|t| t.id != id);
+            }
+        }
+
+        // Start over at tables the players didn't come back to.
+        fn reset_abandoned_tables(&mut self) {
+            for t in 0..self.tables.len() {
+                let table = &self.tables[t];
+                if !matches!(table.abandoned, Some(a) if a.elapsed() > common::PEER_TIMEOUT) {
+                    continue;
+                }
+                info!("SRV: Nobody came back to table {}, reset game", table.id);
+                self.reset_table(t);
+                self.tables[t].gs.action = big2rules::SrvAction::Update;
+                self.broadcast(t);
+            }
+        }
+
+        fn reset_table(&mut self, t: usize) {
+            let table = &mut self.tables[t];
+            table.reset();
+            if let Some(seed) = self.seed {
+                table.gs.set_seed(seed);
+            }
+        }
+
+        fn validate_join(&self, jm: &JoinMessage) -> Result<usize, JoinError> {
+            if jm.magicnumber != common::MAGICNUMBER {
+                return Err(JoinError::MagicNumber);
+            }
+            if jm.version != common::VERSION {
+                return Err(JoinError::Version);
+            }
+            if jm.name.count == 0 {
+                return Err(JoinError::EmptyName);
+            }
+            if !(1..=16).contains(&jm.name.count)
+                || std::str::from_utf8(&jm.name.data[..jm.name.count as usize]).is_err()
+            {
+                return Err(JoinError::InvalidName);
+            }
+            let t = self.table_idx(jm.table).ok_or(JoinError::UnknownTable)?;
+            // The Muon version only plays with four players.
+            if jm.session == 0 && self.tables[t].seats.len() != 4 {
+                return Err(JoinError::PlayerCount);
+            }
+            Ok(t)
+        }
+
+        fn join(&mut self, id: usize, jm: JoinMessage) {
+            if self.seat_of(id).is_some() {
+                return;
+            }
+            let t = match self.validate_join(&jm) {
+                Err(e) => return self.reject(id, e),
+                Ok(t) => t,
+            };
+
+            let table = &mut self.tables[t];
+            if let Some(seat) = GameServer::seat_to_reclaim(table, &jm) {
+                info!(
+                    "SRV: P{} {} back at table {} seat {}",
+                    id,
+                    jm.name.to_string(),
+                    table.id,
+                    seat
+                );
+                // The old connection may not have timed out yet.
+                if let Some(old) = table.seats[seat].peer.replace(id) {
+                    self.drop_peer(old);
+                }
+                self.tables[t].resume();
+                self.set_table(id, jm.table, false);
+                if jm.session != 0 {
+                    self.send_rules(id);
+                }
+                self.tables[t].gs.action = big2rules::SrvAction::Update;
+                self.broadcast(t);
+                return;
+            }
+            let name = jm.name.to_string();
+            if table.seats.iter().any(|s| s.name.to_string() == name) {
+                return self.reject(id, JoinError::DuplicateName);
+            }
+            let seat = match table.seats.iter().position(|s| s.is_empty()) {
+                None => return self.reject(id, JoinError::TableFull),
+                Some(seat) => seat,
+            };
+            info!(
+                "SRV: P{} {} takes table {} seat {}",
+                id, name, table.id, seat
+            );
+
+            table.seats[seat] = Seat {
+                name: jm.name,
+                session: jm.session,
+                is_ready: false,
+                peer: Some(id),
+                bot: None,
+            };
+            table.resume();
+            table.gs.action = big2rules::SrvAction::Update;
+            self.set_table(id, jm.table, false);
+            if jm.session != 0 {
+                self.send_rules(id);
+            }
+            self.broadcast(t);
+        }
+
+        // Not for session 0, a Muon client doesn't know the rules message.
+        fn send_rules(&mut self, id: usize) {
+            let rm = RulesMessage {
+                kind: 12,
+                size: mem::size_of::<RulesMessage>() as u32,
+                rules: self.rules.to_bits(),
+            };
+            let buf = bincode::serialize(&rm).unwrap();
+            self.send(id, &buf);
+        }
+
+        // Tell the peer why it can't join and hang up.
+        fn reject(&mut self, id: usize, reason: JoinError) {
+            info!("SRV: P{} Rejected: {}", id, reason);
+            let rm = RejectMessage {
+                kind: 11,
+                size: RejectMessage::SIZE as u32,
+                reason,
+            };
+            let buf = bincode::serialize(&rm).unwrap();
+            self.send(id, &buf);
+            self.drop_peer(id);
+        }
+
+        fn spectate(&mut self, id: usize, table: u32) {
+            if self.seat_of(id).is_some() {
+                return;
+            }
+            if self.table_idx(table).is_none() {
+                return self.reject(id, JoinError::UnknownTable);
+            }
+            info!("SRV: P{} is spectating table {}", id, table);
+            self.set_table(id, table, true);
+            self.send_rules(id);
+            self.send_state(id);
+        }
+
+        fn set_table(&mut self, id: usize, table: u32, is_spectator: bool) {
+            if let Some(peer) = self.peers.iter_mut().find(|p| p.id == id) {
+                peer.table = Some(table);
+                peer.is_spectator = is_spectator;
+            }
+        }
+
+        // A player that lost the connection gets the seat back when name and session match.
+        // Session 0 is a Muon client, it can only take back a seat that is disconnected.
+        fn seat_to_reclaim(table: &Table, jm: &JoinMessage) -> Option<usize> {
+            table.seats.iter().position(|s| {
+                !s.is_empty()
+                    && s.bot.is_none()
+                    && s.name == jm.name
+                    && s.session == jm.session
+                    && (s.peer.is_none() || s.session != 0)
+            })
+        }
+
+        fn ready(&mut self, id: usize) {
+            let (t, seat) = match self.seat_of(id) {
+                None => return,
+                Some(ts) => ts,
+            };
+            let table = &mut self.tables[t];
+            if !table.is_between_rounds() || table.seats[seat].is_ready {
+                return;
+            }
+            table.seats[seat].is_ready = true;
+
+            if !self.deal(t) {
+                self.tables[t].gs.action = big2rules::SrvAction::Update;
+                self.broadcast(t);
+                return;
+            }
+            // An instant win ends the round at the deal.
+            self.played(t);
+        }
+
+        // Deal the next round when all seats are taken and ready. Players that lost their
+        // connection don't hold up the table.
+        fn deal(&mut self, t: usize) -> bool {
+            self.seat_bots(t);
+            let table = &mut self.tables[t];
+            let all_ready = table
+                .seats
+                .iter()
+                .all(|s| !s.is_empty() && (s.is_ready || s.peer.is_none()));
+            if !table.is_between_rounds() || !all_ready {
+                return false;
+            }
+            if table.gs.is_match_over() {
+                info!("SRV: Table {} start new match", table.id);
+                table.gs.new_match().unwrap();
+            }
+            table.gs.deal(None).unwrap();
+            info!(
+                "SRV: Table {} deal round {}/{} seed {}",
+                table.id, table.gs.round, table.gs.rounds, table.gs.seed
+            );
+            if let big2rules::SrvAction::InstantWin { player, win } = table.gs.action {
+                info!("SRV: Table {} seat {} wins with {}", table.id, player, win);
+            }
+            true
+        }
+
+        // Take the empty seats when all players at the table are ready.
+        fn seat_bots(&mut self, t: usize) {
+            let table = &mut self.tables[t];
+            let strategy = match self.bots {
+                None => return,
+                Some(strategy) => strategy,
+            };
+            let players = table.seats.iter().filter(|s| s.peer.is_some());
+            if !table.is_between_rounds() || players.clone().count() == 0 {
+                return;
+            }
+            if !players.clone().all(|s| s.is_ready) {
+                return;
+            }
+            // Like the deals, the bots play the same for the same seed.
+            let mut rng = StdRng::seed_from_u64(table.gs.seed);
+            for (i, seat) in table.seats.iter_mut().enumerate() {
+                let seed = rng.gen();
+                if !seat.is_empty() {
+                    continue;
+                }
+                let name = format!("{}{}", self.bot_name, i + 1);
+                info!("SRV: Table {} bot {} takes seat {}", table.id, name, i);
+                *seat = Seat {
+                    name: muon::String16::from_string(&name),
+                    is_ready: true,
+                    bot: Some(strategy.bot(seed)),
+                    ..Seat::empty()
+                };
+            }
+        }
+
+        fn play(&mut self, id: usize, cards: u64) {
+            let (t, seat) = match self.seat_of(id) {
+                None => return,
+                Some(ts) => ts,
+            };
+            if let Err(e) = self.tables[t].gs.play(seat as i32, cards) {
+                info!("SRV: P{} Play {:16x} rejected: {}", id, cards, e);
+                self.send_state(id);
+                return;
+            }
+            self.played(t);
+        }
+
+        fn passed(&mut self, t: usize) {
+            self.tables[t].start_turn();
+            self.broadcast(t);
+        }
+
+        // Check for the end of the round and match after a hand is played or dealt.
+        fn played(&mut self, t: usize) {
+            let table = &mut self.tables[t];
+            table.start_turn();
+            if table.is_between_rounds() {
+                info!(
+                    "SRV: Table {} round {} ended! Score: {:?}",
+                    table.id, table.gs.round, table.gs.score
+                );
+                for s in table.seats.iter_mut() {
+                    s.is_ready = s.bot.is_some();
+                }
+                if table.gs.is_match_over() {
+                    self.matches_played += 1;
+                    info!(
+                        "SRV: Table {} match ended! Winner: {:?} Standings: {:?} Matches played {}",
+                        table.id,
+                        table.gs.winner(),
+                        table.gs.standings(),
+                        self.matches_played
+                    );
+                    self.is_shutdown = self.matches != 0 && self.matches_played >= self.matches;
+                    // Open the seats of bots and players that left during the match.
+                    for s in table.seats.iter_mut().filter(|s| s.peer.is_none()) {
+                        *s = Seat::empty();
+                    }
+                }
+            }
+
+            self.broadcast(t);
+        }
+
+        fn pass(&mut self, id: usize) {
+            let (t, seat) = match self.seat_of(id) {
+                None => return,
+                Some(ts) => ts,
+            };
+            if let Err(e) = self.tables[t].gs.pass(seat as i32) {
+                info!("SRV: P{} Pass rejected: {}", id, e);
+                self.send_state(id);
+                return;
+            }
+
+            self.passed(t);
+        }
+
+        // Move for the player on turn. Pass or, when they have to start a new cycle, play
+        // their lowest card.
+        fn auto_move(&mut self, t: usize) -> bool {
+            let table = &mut self.tables[t];
+            let turn = table.gs.turn;
+            if table.gs.board == 0 {
+                let card = table
+                    .gs
+                    .rules
+                    .higher_single_card(0, table.gs.cards[turn as usize]);
+                info!("SRV: Table {} seat {} play {:16x}", table.id, turn, card);
+                if table.gs.play(turn, card).is_err() {
+                    return false;
+                }
+                self.played(t);
+            } else {
+                info!("SRV: Table {} seat {} pass", table.id, turn);
+                if table.gs.pass(turn).is_err() {
+                    return false;
+                }
+                self.passed(t);
+            }
+            true
+        }
+
+        fn check_turn_time(&mut self) {
+            for t in 0..self.tables.len() {
+                let left = self.tables[t].turn_time_left(self.turn_time);
+                if left == Some(Duration::from_secs(0)) && self.tables[t].abandoned.is_none() {
+                    info!("SRV: Table {} turn time is up", self.tables[t].id);
+                    if !self.auto_move(t) {
+                        self.tables[t].start_turn();
+                    }
+                    self.play_for_absent(t);
+                }
+            }
+        }
+
+        fn bot_move(&mut self, t: usize) -> bool {
+            let table = &mut self.tables[t];
+            let turn = table.gs.turn;
+            let view = table.gs.view(turn as usize);
+            let cards = match table.seats[turn as usize].bot.as_mut() {
+                Some(bot) => bot.play(&view),
+                None => return self.auto_move(t),
+            };
+            match cards {
+                Some(cards) if table.gs.play(turn, cards).is_ok() => self.played(t),
+                None if table.gs.pass(turn).is_ok() => self.passed(t),
+                _ => {
+                    info!("SRV: Table {} bot {} can't move", table.id, turn);
+                    return self.auto_move(t);
+                }
+            }
+            true
+        }
+
+        // Keep the game going for bots and seats without a connection. Wait for the players
+        // at a table where nobody is connected.
+        fn play_for_absent(&mut self, t: usize) {
+            if self.tables[t].abandoned.is_some() {
+                return;
+            }
+            loop {
+                let table = &self.tables[t];
+                let turn = table.gs.turn;
+                if turn < 0 || table.seats[turn as usize].peer.is_some() {
+                    break;
+                }
+                let moved = if table.seats[turn as usize].bot.is_some() {
+                    self.bot_move(t)
+                } else {
+                    info!("SRV: Table {} seat {} is absent", table.id, turn);
+                    self.auto_move(t)
+                };
+                if !moved {
+                    break;
+                }
+            }
+
+            let table = &self.tables[t];
+            if table.gs.phase == big2rules::Phase::RoundOver && self.deal(t) {
+                self.played(t);
+            }
+        }
+
+        fn close(&mut self, id: usize) {
+            let peer = self.peers.iter().position(|p| p.id == id);
+            if peer.is_none() {
+                return;
+            }
+            self.peers.remove(peer.unwrap());
+
+            if let Some((t, seat)) = self.seat_of(id) {
+                let table = &mut self.tables[t];
+                info!("SRV: P{} left table {} seat {}", id, table.id, seat);
+                if table.is_between_matches() {
+                    table.seats[seat] = Seat::empty();
+                } else {
+                    table.seats[seat].peer = None;
+                }
+                // Nobody left at the table, start over for the next players. When the players
+                // only lost the connection, give them some time to come back.
+                if table.is_idle() {
+                    info!("SRV: Table {} is empty, reset game", table.id);
+                    self.reset_table(t);
+                } else if table.is_unattended() {
+                    info!("SRV: Nobody is connected to table {}", table.id);
+                    table.abandoned = Some(Instant::now());
+                }
+                let table = &mut self.tables[t];
+                table.gs.action = big2rules::SrvAction::Update;
+                self.broadcast(t);
+            }
+        }
+
+        fn drop_peer(&mut self, id: usize) {
+            if let Some(peer) = self.peers.iter().find(|p| p.id == id) {
+                let _ = peer.ts.shutdown(Shutdown::Both);
+            }
+        }
+
+        fn send_state(&mut self, id: usize) {
+            let sm = match self.seat_of(id) {
+                Some((t, seat)) => {
+                    let hand = self.tables[t].gs.cards[seat];
+                    if hand.count_ones() > 13 {
+                        let hm = HandMessage {
+                            kind: 13,
+                            size: mem::size_of::<HandMessage>() as u32,
+                            cards: hand,
+                        };
+                        let buf = bincode::serialize(&hm).unwrap();
+                        self.send(id, &buf);
+                    }
+                    self.tables[t].state_message(Some(seat), self.turn_time)
+                }
+                None => {
+                    let table = self.peers.iter().find(|p| p.id == id).and_then(|p| p.table);
+                    match table.and_then(|table| self.table_idx(table)) {
+                        None => return,
+                        Some(t) => self.tables[t].state_message(None, self.turn_time),
+                    }
+                }
+            };
+            let buf = bincode::serialize(&sm).unwrap();
+            self.send(id, &buf);
+        }
+
+        fn broadcast(&mut self, t: usize) {
+            let table = &self.tables[t];
+            let ids: Vec<usize> = self
+                .peers
+                .iter()
+                .filter(|p| p.is_spectator && p.table == Some(table.id))
+                .map(|p| p.id)
+                .chain(table.seats.iter().filter_map(|s| s.peer))
+                .collect();
+            for id in ids {
+                self.send_state(id);
+            }
+        }
+
+        fn send(&mut self, id: usize, buf: &[u8]) {
+            if let Some(peer) = self.peers.iter_mut().find(|p| p.id == id) {
+                trace!("SRV: P{} PUSH: {:x?}", id, buf);
+                if let Err(e) = peer.ts.write_all(buf) {
+                    error!("SRV: P{} Error write. {}", id, e);
+                    let _ = peer.ts.shutdown(Shutdown::Both);
+                }
+            }
+        }
+    }
+
+    pub fn start(port: u16, rounds: u8) -> Result<thread::JoinHandle<()>, io::Error> {
+        GameServer::bind(port, rounds, 0)?.spawn()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // #[test]
+    // fn a_connect() {
+    //  let name = String::from("René");
+    //  let JM: JoinMessage = client::joinMessage(&name);
+    //  let JMB = bincode::serialize(&JM).unwrap();
+    //  println!("{:x?}", JMB);
+    //  //                       12356789T123456
+    //  let name = String::from("René to long to5123123");
+    //  let JM: JoinMessage = client::joinMessage(&name);
+    //  let JMB = bincode::serialize(&JM).unwrap();
+    //  println!("{:x?}", JMB);
+    // }
+    /*  #[test]
+    fn b_connect() {
+        let sm_size = std::mem::size_of::<StateMessage>();
+        let eb = &[0u8; std::mem::size_of::<StateMessage>()];
+        let mut sm: StateMessage = bincode::deserialize(eb).unwrap();
+        sm.size = sm_size as u32;
+        sm.action = StateMessageActionType::play;
+
+        let smb = bincode::serialize(&sm).unwrap();
+        println!("{:x?}", smb);
+    } */
+    #[test]
+    fn c_statemessage_respone() {
+        let buffer: &[u8] = &[
+            5, 0, 0, 0, 0xe0, 0, 0, 0, 1, 0, 0, 0, 8, 0, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 0x15, 7,
+            0x37, 0x28, 0x38, 0x39, 0xa, 0x2b, 0x3b, 0x2c, 0x1d, 0x3d, 2, 0, 0, 0, 0xd, 0, 0, 0,
+            0x54, 0x69, 0x6b, 0x6b, 0x69, 0x65, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 6, 0, 0, 0, 0, 0, 0,
+            0, 9, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0x68, 0x6f, 0x73, 0x74, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 4, 0, 0, 0, 0, 0, 0, 0, 7, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0x52, 0x65,
+            0x6e, 0x65, 0x31, 0x32, 0x33, 0, 0, 0, 0, 0, 0, 0, 0, 0, 7, 0, 0, 0, 0, 0, 0, 0, 0xb,
+            0, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 0x52, 0x65, 0x6e, 0x65, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0xd, 0, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 0x16, 0x26, 0, 0,
+            0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0,
+        ];
+
+        let sm = StateMessage::new(Some(buffer));
 
         let mut mycards: u64 = 0;
         for c in 0..sm.your_hand.count as usize {
@@ -909,11 +2426,107 @@ mod tests {
         assert_eq!(trail & 0xFFFF_FFFF_FFFF_F000, cards);
     }
 
+    #[test]
+    fn statemessage_from_srv_game_state() {
+        let mut gs = big2rules::SrvGameState::new(8);
+        gs.deal(Some(&[
+            0x1111_1111_1111_1000,
+            0x2222_2222_2222_2000,
+            0x4444_4444_4444_4000,
+            0x8888_8888_8888_8000,
+        ]))
+        .unwrap();
+
+        for p in 0..4 {
+            let sm = StateMessage::from_srv_game_state(&gs, p);
+            assert_eq!(sm.kind, 5);
+            assert_eq!(sm.size as usize, mem::size_of::<StateMessage>());
+            assert_eq!(sm.round, 1);
+            assert_eq!(sm.num_rounds, 8);
+            assert_eq!(sm.turn, 0);
+            assert_eq!(sm.your_index, p);
+            assert_eq!(sm.your_hand.to_card(), gs.cards[p as usize]);
+            assert_eq!(sm.action.action_type, StateMessageActionType::DEAL);
+            assert_eq!(sm.action_msg(), gs.cards[p as usize] | 0x400 | p as u64);
+        }
+
+        gs.play(0, 0x1000).unwrap();
+        gs.pass(1).unwrap();
+
+        let sm = StateMessage::from_srv_game_state(&gs, 2);
+        assert_eq!(sm.your_hand.to_card(), 0x4444_4444_4444_4000);
+        assert_eq!(sm.turn, 2);
+        assert_eq!(sm.players[0].num_cards, 12);
+        assert_eq!(sm.players[1].num_cards, 13);
+        assert!(sm.players[1].has_passed_this_cycle);
+        assert!(!sm.players[0].has_passed_this_cycle);
+        assert_eq!(sm.board.into_card().unwrap(), 0x1000);
+        assert_eq!(sm.action.action_type, StateMessageActionType::PASS);
+        assert_eq!(sm.action.player, 1);
+        assert!(!sm.action.is_end_of_cycle);
+
+        gs.pass(2).unwrap();
+        gs.pass(3).unwrap();
+        let sm = StateMessage::from_srv_game_state(&gs, 0);
+        assert_eq!(sm.turn, 0);
+        assert_eq!(sm.board.into_card().unwrap(), 0x1000);
+        assert!(sm.action.is_end_of_cycle);
+        assert_eq!(sm.action_msg(), 0x1000 | 0x100 | 0x3);
+
+        gs.play(0, 0x10000).unwrap();
+        let sm = StateMessage::from_srv_game_state(&gs, 3);
+        assert_eq!(sm.your_hand.to_card(), 0x8888_8888_8888_8000);
+        assert_eq!(sm.board.count, 0);
+        assert_eq!(sm.action.action_type, StateMessageActionType::PLAY);
+        assert_eq!(sm.action.cards.into_card().unwrap(), 0x10000);
+        assert_eq!(sm.action_msg(), 0x10000 | 0x10);
+
+        // No seat, no cards.
+        let sm = StateMessage::from_srv_game_state(&gs, -1);
+        assert_eq!(sm.your_index, -1);
+        assert_eq!(sm.your_hand.count, 0);
+        assert_eq!(sm.your_hand.data, [0; 16]);
+        assert_eq!(sm.instant_win(), None);
+
+        // Every hand is a dragon, the tie goes to the player with the 3♦.
+        let rules = big2rules::rules::RuleSet::preset("instant-win").unwrap();
+        let mut gs = big2rules::SrvGameState::with_rules(8, rules);
+        gs.deal(Some(&[
+            0x1111_1111_1111_1000,
+            0x2222_2222_2222_2000,
+            0x4444_4444_4444_4000,
+            0x8888_8888_8888_8000,
+        ]))
+        .unwrap();
+        let sm = StateMessage::from_srv_game_state(&gs, 1);
+        assert_eq!(sm.turn, -1);
+        assert_eq!(sm.action.action_type, StateMessageActionType::DEAL);
+        assert_eq!(sm.action.player, 0);
+        assert_eq!(sm.instant_win(), Some(big2rules::rules::InstantWin::Dragon));
+        assert_eq!(sm.players[0].delta_score, 3 * 39);
+        assert_eq!(sm.players[1].num_cards, 13);
+    }
+
     #[test]
     fn message_struct_size() {
         assert_eq!(std::mem::size_of::<Message>(), 264);
     }
 
+    #[test]
+    fn rejectmessage_size() {
+        let rm = RejectMessage {
+            kind: 11,
+            size: RejectMessage::SIZE as u32,
+            reason: JoinError::UnknownTable,
+        };
+        let buf = bincode::serialize(&rm).unwrap();
+        assert_eq!(buf.len(), RejectMessage::SIZE);
+
+        // A reason this version doesn't know.
+        let buf: &[u8] = &[11, 0, 0, 0, 12, 0, 0, 0, 99, 0, 0, 0];
+        assert!(bincode::deserialize::<RejectMessage>(buf).is_err());
+    }
+
     #[test]
     fn statemessage_struct_size() {
         assert_eq!(std::mem::size_of::<StateMessage>(), 224);
@@ -13,7 +13,8 @@ mod tests_big2rules {
 
         gs.deal(Some(
             &big2rules_srv_test_vectors::gameserver_vectors::TEST_VECTOR_CARDS_GAME1[0..4],
-        ));
+        ))
+        .unwrap();
 
         assert_eq!(gs.turn, 3);
         assert_eq!(gs.round, 1);
@@ -32,7 +33,7 @@ mod tests_big2rules {
                     println!("UPDATE {:16x}", play);
                     if *play == 0x111_1800 {
                         cp += 4;
-                        gs.deal(Some(&big2rules_srv_test_vectors::gameserver_vectors::TEST_VECTOR_CARDS_GAME1[cp..cp + 4]));
+                        gs.deal(Some(&big2rules_srv_test_vectors::gameserver_vectors::TEST_VECTOR_CARDS_GAME1[cp..cp + 4])).unwrap();
                         println!("++ Start new game, round {}/{}", gs.round, gs.rounds);
                     }
                 }
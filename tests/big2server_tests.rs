@@ -0,0 +1,822 @@
+#[cfg(test)]
+mod tests_big2server {
+    use big2::{big2rules, network};
+    use std::{
+        io::{self, Read, Write},
+        net::{Shutdown, TcpListener, TcpStream},
+        sync::{Arc, Mutex},
+        thread,
+        time::Duration,
+    };
+
+    type Connections = Arc<Mutex<Vec<(TcpStream, TcpStream)>>>;
+
+    fn wait_for_state(
+        tc: &mut network::client::TcpClient,
+        action: network::StateMessageActionType,
+    ) -> network::StateMessage {
+        for _ in 0..500 {
+            if let Some(sm) = tc.check_buffer().unwrap() {
+                if sm.action.action_type == action {
+                    return sm;
+                }
+                continue;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("No {:?} StateMessage received!", action);
+    }
+
+    fn wait_for_table_list(tc: &mut network::client::TcpClient) -> network::TableListMessage {
+        for _ in 0..500 {
+            tc.check_buffer().unwrap();
+            if let Some(tlm) = tc.table_list() {
+                return tlm;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("No TableListMessage received!");
+    }
+
+    fn wait_for_error(tc: &mut network::client::TcpClient) -> io::Error {
+        for _ in 0..500 {
+            if let Err(e) = tc.check_buffer() {
+                return e;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("Join not rejected!");
+    }
+
+    // Connect like the host and auto-play clients, they find the lobby before they join.
+    fn connect(addr: &str) -> network::client::TcpClient {
+        let mut tc = network::client::TcpClient::connect(addr.to_string()).unwrap();
+        assert!(tc.detect_lobby(Duration::from_secs(5)).unwrap());
+        tc
+    }
+
+    // Forward every connection on `port` to the server, in the order they come in. Cut a
+    // connection by shutting down its pair of streams.
+    fn proxy(port: u16, server: String) -> Connections {
+        let listener = TcpListener::bind(("127.0.0.1", port)).unwrap();
+        let connections: Connections = Arc::new(Mutex::new(Vec::new()));
+        let list = connections.clone();
+        thread::spawn(move || {
+            for client in listener.incoming() {
+                let client = client.unwrap();
+                let upstream = TcpStream::connect(&server).unwrap();
+                let pipes = vec![
+                    (client.try_clone().unwrap(), upstream.try_clone().unwrap()),
+                    (upstream.try_clone().unwrap(), client.try_clone().unwrap()),
+                ];
+                for (mut from, mut to) in pipes {
+                    thread::spawn(move || {
+                        let _ = io::copy(&mut from, &mut to);
+                        let _ = to.shutdown(Shutdown::Both);
+                    });
+                }
+                list.lock().unwrap().push((client, upstream));
+            }
+        });
+        connections
+    }
+
+    fn muon_join_msg(magicnumber: u32, version: u32, name: &str) -> Vec<u8> {
+        let mut jm = Vec::new();
+        for v in [1, 36, magicnumber, version].iter() {
+            jm.extend_from_slice(&v.to_le_bytes());
+        }
+        let mut data = [0u8; 16];
+        data[..name.len()].copy_from_slice(name.as_bytes());
+        jm.extend_from_slice(&data);
+        jm.extend_from_slice(&(name.len() as u32).to_le_bytes());
+        jm
+    }
+
+    #[test]
+    fn server_deals_when_table_is_ready() {
+        let port: u16 = 27291;
+        network::server::start(port, 2).unwrap();
+
+        let mut clients = Vec::new();
+        for p in 0..4 {
+            let mut tc =
+                network::client::TcpClient::connect(format!("127.0.0.1:{}", port)).unwrap();
+            tc.send_join_msg(&format!("Player{}", p)).unwrap();
+            let sm = wait_for_state(&mut tc, network::StateMessageActionType::UPDATE);
+            assert_eq!(sm.turn, -1);
+            assert_eq!(sm.your_index, p);
+            assert_eq!(
+                sm.players[p as usize].name.to_string(),
+                format!("Player{}", p)
+            );
+            clients.push(tc);
+        }
+
+        for tc in clients.iter_mut() {
+            tc.action_ready().unwrap();
+        }
+
+        let mut all_cards: u64 = 0;
+        let mut turn = -1;
+        for (p, tc) in clients.iter_mut().enumerate() {
+            let sm = wait_for_state(tc, network::StateMessageActionType::DEAL);
+            assert_eq!(sm.round, 1);
+            assert_eq!(sm.num_rounds, 2);
+            assert_eq!(sm.your_index, p as i32);
+            assert_eq!(sm.your_hand.count, 13);
+            let hand = sm.your_hand.to_card();
+            assert_eq!(all_cards & hand, 0);
+            all_cards |= hand;
+            if hand & 0x1000 != 0 {
+                turn = p as i32;
+            }
+            for player in sm.players.iter() {
+                assert_eq!(player.num_cards, 13);
+                assert!(player.is_ready);
+            }
+        }
+        assert_eq!(all_cards, 0xFFFF_FFFF_FFFF_F000);
+
+        // The 3♦ opens the game.
+        let tc = &mut clients[turn as usize];
+        tc.action_play(0x1000).unwrap();
+        let sm = wait_for_state(tc, network::StateMessageActionType::PLAY);
+        assert_eq!(sm.action.player, turn);
+        assert_eq!(sm.action.cards.into_card().unwrap(), 0x1000);
+        assert_eq!(sm.board.count, 0);
+        assert_eq!(sm.your_hand.count, 12);
+        assert_eq!(sm.turn, (turn + 1) & 0x3);
+    }
+
+    #[test]
+    fn server_shuts_down_after_last_match() {
+        let port: u16 = 27292;
+        let mut srv = network::server::GameServer::bind(port, 1, 1).unwrap();
+        let srv_thread = thread::spawn(move || srv.run());
+
+        let mut clients = Vec::new();
+        for p in 0..4 {
+            let mut tc =
+                network::client::TcpClient::connect(format!("127.0.0.1:{}", port)).unwrap();
+            tc.send_join_msg(&format!("Player{}", p)).unwrap();
+            wait_for_state(&mut tc, network::StateMessageActionType::UPDATE);
+            tc.action_ready().unwrap();
+            clients.push(tc);
+        }
+
+        // Play the lowest single card that beats the board, otherwise pass.
+        let mut is_running = true;
+        while is_running {
+            for tc in clients.iter_mut() {
+                let sm = match tc.check_buffer() {
+                    Err(_) => {
+                        is_running = false;
+                        break;
+                    }
+                    Ok(None) => continue,
+                    Ok(Some(sm)) => sm,
+                };
+                if sm.turn != sm.your_index {
+                    continue;
+                }
+                let hand = sm.your_hand.to_card();
+                let board = if sm.action.is_end_of_cycle {
+                    0
+                } else if sm.action.action_type == network::StateMessageActionType::PLAY {
+                    sm.action.cards.into_card().unwrap()
+                } else {
+                    sm.board.into_card().unwrap()
+                };
+                let card = if board.count_ones() > 1 {
+                    0
+                } else {
+                    big2rules::rules::higher_single_card(board, hand)
+                };
+                if card == 0 {
+                    tc.action_pass().unwrap();
+                } else {
+                    tc.action_play(card).unwrap();
+                }
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        srv_thread.join().unwrap();
+    }
+
+    #[test]
+    fn server_plays_for_disconnected_seat() {
+        let port: u16 = 27293;
+        network::server::start(port, 1).unwrap();
+
+        let mut clients = Vec::new();
+        for p in 0..4 {
+            let mut tc =
+                network::client::TcpClient::connect(format!("127.0.0.1:{}", port)).unwrap();
+            tc.send_join_msg(&format!("Player{}", p)).unwrap();
+            wait_for_state(&mut tc, network::StateMessageActionType::UPDATE);
+            tc.action_ready().unwrap();
+            clients.push(tc);
+        }
+
+        let mut turn = -1;
+        for tc in clients.iter_mut() {
+            let sm = wait_for_state(tc, network::StateMessageActionType::DEAL);
+            turn = sm.turn;
+        }
+
+        // The player with the 3♦ leaves, the server plays the 3♦ for that seat.
+        let tc = clients.remove(turn as usize);
+        network::client::disconnect(tc);
+
+        let tc = &mut clients[0];
+        let sm = wait_for_state(tc, network::StateMessageActionType::UPDATE);
+        assert!(sm.players[turn as usize].is_disconnected());
+        assert_eq!(
+            sm.players[turn as usize].name.to_string(),
+            format!("Player{}", turn)
+        );
+        let sm = wait_for_state(tc, network::StateMessageActionType::PLAY);
+        assert_eq!(sm.action.player, turn);
+        assert_eq!(sm.action.cards.into_card().unwrap(), 0x1000);
+        assert_eq!(sm.turn, (turn + 1) & 0x3);
+    }
+
+    #[test]
+    fn server_gives_seat_back_after_reconnect() {
+        let port: u16 = 27294;
+        let addr = format!("127.0.0.1:{}", port);
+        network::server::start(port, 1).unwrap();
+
+        let mut clients = Vec::new();
+        for p in 0..4 {
+            let mut tc = connect(&addr);
+            tc.send_join_msg(&format!("Player{}", p)).unwrap();
+            wait_for_state(&mut tc, network::StateMessageActionType::UPDATE);
+            tc.action_ready().unwrap();
+            clients.push(tc);
+        }
+
+        let mut hands = Vec::new();
+        let mut turn = -1;
+        for tc in clients.iter_mut() {
+            let sm = wait_for_state(tc, network::StateMessageActionType::DEAL);
+            hands.push(sm.your_hand.to_card());
+            turn = sm.turn;
+        }
+
+        // A player that is not on turn leaves and comes back with the same session.
+        let seat = ((turn + 2) & 0x3) as usize;
+        let tc = clients.remove(seat);
+        let session = tc.session();
+        network::client::disconnect(tc);
+
+        let sm = wait_for_state(&mut clients[0], network::StateMessageActionType::UPDATE);
+        assert!(sm.players[seat].is_disconnected());
+
+        let mut tc = network::client::TcpClient::connect_session(addr, session).unwrap();
+        assert!(tc.detect_lobby(Duration::from_secs(5)).unwrap());
+        tc.send_join_msg(&format!("Player{}", seat)).unwrap();
+        let sm = wait_for_state(&mut tc, network::StateMessageActionType::UPDATE);
+        assert_eq!(sm.your_index, seat as i32);
+        assert_eq!(sm.your_hand.to_card(), hands[seat]);
+        assert_eq!(sm.turn, turn);
+        assert!(!sm.players[seat].is_disconnected());
+    }
+
+    #[test]
+    fn server_sends_state_to_spectator() {
+        let port: u16 = 27295;
+        let addr = format!("127.0.0.1:{}", port);
+        network::server::start(port, 1).unwrap();
+
+        let mut spectator = network::client::TcpClient::connect(addr.clone()).unwrap();
+        spectator.send_spectate_msg(0).unwrap();
+        let sm = wait_for_state(&mut spectator, network::StateMessageActionType::UPDATE);
+        assert!(sm.is_spectator());
+        assert_eq!(sm.your_index, -1);
+
+        let mut clients = Vec::new();
+        for p in 0..4 {
+            let mut tc = network::client::TcpClient::connect(addr.clone()).unwrap();
+            tc.send_join_msg(&format!("Player{}", p)).unwrap();
+            wait_for_state(&mut tc, network::StateMessageActionType::UPDATE);
+            tc.action_ready().unwrap();
+            clients.push(tc);
+        }
+
+        // The spectator doesn't take a seat and sees no cards.
+        let sm = wait_for_state(&mut spectator, network::StateMessageActionType::DEAL);
+        assert_eq!(sm.your_index, -1);
+        assert_eq!(sm.your_hand.count, 0);
+        for (p, player) in sm.players.iter().enumerate() {
+            assert_eq!(player.name.to_string(), format!("Player{}", p));
+            assert_eq!(player.num_cards, 13);
+        }
+
+        // Actions of a spectator are ignored.
+        spectator.action_play(0x1000).unwrap();
+        spectator.action_pass().unwrap();
+        let tc = &mut clients[sm.turn as usize];
+        tc.action_play(0x1000).unwrap();
+        let sm = wait_for_state(&mut spectator, network::StateMessageActionType::PLAY);
+        assert_eq!(sm.action.player, (sm.turn + 3) & 0x3);
+        assert_eq!(sm.action.cards.into_card().unwrap(), 0x1000);
+    }
+
+    #[test]
+    fn server_hosts_multiple_tables() {
+        let port: u16 = 27296;
+        let addr = format!("127.0.0.1:{}", port);
+        network::server::start(port, 1).unwrap();
+
+        let mut lobby = network::client::TcpClient::connect(addr.clone()).unwrap();
+        lobby.send_list_tables_msg().unwrap();
+        let tlm = wait_for_table_list(&mut lobby);
+        assert_eq!(tlm.tables().len(), 1);
+        assert_eq!(tlm.tables()[0].id, network::common::DEFAULT_TABLE);
+        assert_eq!(tlm.your_table, -1);
+
+        lobby.send_create_table_msg(2).unwrap();
+        let tlm = wait_for_table_list(&mut lobby);
+        assert_eq!(tlm.tables().len(), 2);
+        let table = tlm.your_table as u32;
+        assert_ne!(table, network::common::DEFAULT_TABLE);
+
+        let mut clients = Vec::new();
+        for p in 0..4 {
+            let mut tc = connect(&addr);
+            tc.send_join_table_msg(&format!("Player{}", p), table)
+                .unwrap();
+            let sm = wait_for_state(&mut tc, network::StateMessageActionType::UPDATE);
+            assert_eq!(sm.your_index, p);
+            assert_eq!(sm.num_rounds, 2);
+            clients.push(tc);
+        }
+
+        // A Muon client has no lobby and lands at the default table.
+        let mut muon = TcpStream::connect(&addr).unwrap();
+        let jm = muon_join_msg(
+            network::common::MAGICNUMBER,
+            network::common::VERSION,
+            "Muon",
+        );
+        muon.write_all(&jm).unwrap();
+
+        for _ in 0..100 {
+            lobby.send_list_tables_msg().unwrap();
+            let tlm = wait_for_table_list(&mut lobby);
+            let players: Vec<u32> = tlm.tables().iter().map(|t| t.num_players).collect();
+            if players == [1, 4] {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("Muon client did not join the default table!");
+    }
+
+    #[test]
+    fn server_moves_when_turn_time_is_up() {
+        let port: u16 = 27297;
+        let mut srv = network::server::GameServer::bind(port, 1, 0).unwrap();
+        srv.set_turn_time(1);
+        srv.spawn().unwrap();
+
+        let mut clients = Vec::new();
+        for p in 0..4 {
+            let mut tc =
+                network::client::TcpClient::connect(format!("127.0.0.1:{}", port)).unwrap();
+            tc.send_join_msg(&format!("Player{}", p)).unwrap();
+            wait_for_state(&mut tc, network::StateMessageActionType::UPDATE);
+            tc.action_ready().unwrap();
+            clients.push(tc);
+        }
+
+        let tc = &mut clients[0];
+        let sm = wait_for_state(tc, network::StateMessageActionType::DEAL);
+        assert_eq!(sm.action.turn_time, 1);
+        let turn = sm.turn;
+
+        // Nobody moves. The player on turn leads with the lowest card, the next one passes.
+        let sm = wait_for_state(tc, network::StateMessageActionType::PLAY);
+        assert_eq!(sm.action.player, turn);
+        assert_eq!(sm.action.cards.into_card().unwrap(), 0x1000);
+        assert_eq!(sm.action.turn_time, 1);
+        let sm = wait_for_state(tc, network::StateMessageActionType::PASS);
+        assert_eq!(sm.action.player, (turn + 1) & 0x3);
+    }
+
+    #[test]
+    fn server_restarts_turn_time_after_highest_card() {
+        let port: u16 = 27306;
+        let mut srv = network::server::GameServer::bind(port, 1, 0).unwrap();
+        srv.set_turn_time(3);
+        srv.spawn().unwrap();
+
+        let mut clients = Vec::new();
+        for p in 0..4 {
+            let mut tc =
+                network::client::TcpClient::connect(format!("127.0.0.1:{}", port)).unwrap();
+            tc.send_join_msg(&format!("Player{}", p)).unwrap();
+            wait_for_state(&mut tc, network::StateMessageActionType::UPDATE);
+            tc.action_ready().unwrap();
+            clients.push(tc);
+        }
+
+        let two_of_spades = 0x8000_0000_0000_0000;
+        let mut holder = 0;
+        let mut turn = -1;
+        for (p, tc) in clients.iter_mut().enumerate() {
+            let sm = wait_for_state(tc, network::StateMessageActionType::DEAL);
+            if sm.your_hand.to_card() & two_of_spades != 0 {
+                holder = p as i32;
+            }
+            turn = sm.turn;
+        }
+
+        // Open with the 3♦ and pass until the 2♠ can be played.
+        clients[turn as usize].action_play(0x1000).unwrap();
+        let sm = wait_for_state(&mut clients[0], network::StateMessageActionType::PLAY);
+        turn = sm.turn;
+        while turn != holder {
+            clients[turn as usize].action_pass().unwrap();
+            let sm = wait_for_state(&mut clients[0], network::StateMessageActionType::PASS);
+            turn = sm.turn;
+        }
+
+        // Nothing beats the 2♠, the player keeps the turn and gets the full time again.
+        thread::sleep(Duration::from_secs(2));
+        clients[holder as usize].action_play(two_of_spades).unwrap();
+        let sm = wait_for_state(&mut clients[0], network::StateMessageActionType::PLAY);
+        assert_eq!(sm.action.player, holder);
+        assert_eq!(sm.turn, holder);
+        assert_eq!(sm.action.turn_time, 3);
+    }
+
+    #[test]
+    fn server_fills_empty_seats_with_bots() {
+        play_round_with_bots(27298, big2rules::bot::Strategy::Lowest);
+    }
+
+    #[test]
+    fn server_plays_heuristic_bots() {
+        play_round_with_bots(27308, big2rules::bot::Strategy::Heuristic);
+    }
+
+    // One player and three bots of `strategy` play a round.
+    fn play_round_with_bots(port: u16, strategy: big2rules::bot::Strategy) {
+        let mut srv = network::server::GameServer::bind(port, 1, 0).unwrap();
+        srv.set_bots(Some(strategy), "Bot");
+        srv.spawn().unwrap();
+
+        let mut tc = network::client::TcpClient::connect(format!("127.0.0.1:{}", port)).unwrap();
+        tc.send_join_msg(&String::from("Human")).unwrap();
+        wait_for_state(&mut tc, network::StateMessageActionType::UPDATE);
+        tc.action_ready().unwrap();
+
+        let sm = wait_for_state(&mut tc, network::StateMessageActionType::DEAL);
+        assert_eq!(sm.your_index, 0);
+        for (p, player) in sm.players.iter().enumerate().skip(1) {
+            assert_eq!(player.name.to_string(), format!("Bot{}", p + 1));
+            assert!(!player.is_disconnected());
+        }
+
+        // Play against the bots until the round is over, starting with the deal.
+        let mut pending = Some(sm);
+        for _ in 0..10000 {
+            let sm = match pending.take().or_else(|| tc.check_buffer().unwrap()) {
+                None => {
+                    thread::sleep(Duration::from_millis(1));
+                    continue;
+                }
+                Some(sm) => sm,
+            };
+            if sm.turn == -1 {
+                assert_eq!(sm.action.action_type, network::StateMessageActionType::PLAY);
+                let total: i32 = sm.players.iter().map(|p| p.delta_score).sum();
+                assert_eq!(total, 0);
+                return;
+            }
+            if sm.turn != sm.your_index {
+                continue;
+            }
+            let board = if sm.action.is_end_of_cycle {
+                0
+            } else if sm.action.action_type == network::StateMessageActionType::PLAY {
+                sm.action.cards.into_card().unwrap()
+            } else {
+                sm.board.into_card().unwrap()
+            };
+            let rules = big2rules::rules::RuleSet::default();
+            let view = big2rules::bot::View::new(rules, sm.your_hand.to_card(), board);
+            match big2rules::bot::Strategy::Lowest.bot(0).play(&view) {
+                None => tc.action_pass().unwrap(),
+                Some(cards) => tc.action_play(cards).unwrap(),
+            };
+        }
+        panic!("Round did not end!");
+    }
+
+    #[test]
+    fn server_rejects_invalid_joins() {
+        let port: u16 = 27299;
+        let addr = format!("127.0.0.1:{}", port);
+        network::server::start(port, 1).unwrap();
+
+        // A wrong magic number or version gets a RejectMessage and the connection is closed.
+        let magic = network::common::MAGICNUMBER;
+        let version = network::common::VERSION;
+        for (jm, reason) in [
+            (
+                muon_join_msg(0x1234, version, "Kim"),
+                network::JoinError::MagicNumber,
+            ),
+            (
+                muon_join_msg(magic, version + 1, "Kim"),
+                network::JoinError::Version,
+            ),
+            (
+                muon_join_msg(magic, version, ""),
+                network::JoinError::EmptyName,
+            ),
+        ]
+        .iter()
+        {
+            let mut ts = TcpStream::connect(&addr).unwrap();
+            ts.write_all(jm).unwrap();
+            let mut buf = Vec::new();
+            ts.read_to_end(&mut buf).unwrap();
+            let rm: network::RejectMessage = bincode::deserialize(&buf).unwrap();
+            assert_eq!(rm.kind, 11);
+            assert_eq!(rm.reason, *reason);
+        }
+
+        let mut clients = Vec::new();
+        for p in 0..4 {
+            let mut tc = network::client::TcpClient::connect(addr.clone()).unwrap();
+            tc.send_join_msg(&format!("Player{}", p)).unwrap();
+            wait_for_state(&mut tc, network::StateMessageActionType::UPDATE);
+            clients.push(tc);
+        }
+
+        // The client turns the rejection into an error.
+        for (name, table, reason) in [
+            ("Player1", 1, network::JoinError::UnknownTable),
+            ("Player1", 0, network::JoinError::DuplicateName),
+            ("Player4", 0, network::JoinError::TableFull),
+        ]
+        .iter()
+        {
+            let mut tc = connect(&addr);
+            tc.send_join_table_msg(&name.to_string(), *table).unwrap();
+            let e = wait_for_error(&mut tc);
+            assert_eq!(e.kind(), io::ErrorKind::ConnectionRefused);
+            assert_eq!(e.to_string(), format!("Join rejected: {}!", reason));
+        }
+    }
+
+    #[test]
+    fn server_announces_rules() {
+        let port: u16 = 27300;
+        let addr = format!("127.0.0.1:{}", port);
+        let rules = big2rules::rules::RuleSet::preset("clubs-low").unwrap();
+        let mut srv = network::server::GameServer::bind(port, 1, 0).unwrap();
+        srv.set_rules(rules);
+        srv.spawn().unwrap();
+
+        let mut clients = Vec::new();
+        for p in 0..4 {
+            let mut tc = connect(&addr);
+            tc.send_join_msg(&format!("Player{}", p)).unwrap();
+            wait_for_state(&mut tc, network::StateMessageActionType::UPDATE);
+            assert_eq!(tc.rules(), Some(rules));
+            clients.push(tc);
+        }
+
+        let mut spectator = network::client::TcpClient::connect(addr).unwrap();
+        spectator.send_spectate_msg(0).unwrap();
+        wait_for_state(&mut spectator, network::StateMessageActionType::UPDATE);
+        assert_eq!(spectator.rules(), Some(rules));
+
+        for tc in clients.iter_mut() {
+            tc.action_ready().unwrap();
+        }
+
+        // The 3♣ opens the match.
+        for tc in clients.iter_mut() {
+            let sm = wait_for_state(tc, network::StateMessageActionType::DEAL);
+            let has_opening_card = sm.your_hand.to_card() & 0x2000 != 0;
+            assert_eq!(sm.turn == sm.your_index, has_opening_card);
+        }
+    }
+
+    #[test]
+    fn server_deals_three_players() {
+        let port: u16 = 27301;
+        let addr = format!("127.0.0.1:{}", port);
+        let rules = big2rules::rules::RuleSet {
+            players: 3,
+            ..big2rules::rules::RuleSet::STANDARD
+        };
+        let mut srv = network::server::GameServer::bind(port, 1, 0).unwrap();
+        srv.set_rules(rules);
+        srv.spawn().unwrap();
+
+        // The Muon version can't play with three players.
+        let jm = muon_join_msg(
+            network::common::MAGICNUMBER,
+            network::common::VERSION,
+            "Kim",
+        );
+        let mut ts = TcpStream::connect(&addr).unwrap();
+        ts.write_all(&jm).unwrap();
+        let mut buf = Vec::new();
+        ts.read_to_end(&mut buf).unwrap();
+        let rm: network::RejectMessage = bincode::deserialize(&buf).unwrap();
+        assert_eq!(rm.reason, network::JoinError::PlayerCount);
+
+        let mut clients = Vec::new();
+        for p in 0..3 {
+            let mut tc = connect(&addr);
+            tc.send_join_msg(&format!("Player{}", p)).unwrap();
+            wait_for_state(&mut tc, network::StateMessageActionType::UPDATE);
+            assert_eq!(tc.rules(), Some(rules));
+            clients.push(tc);
+        }
+        let mut tc = connect(&addr);
+        tc.send_join_msg(&"Player3".to_string()).unwrap();
+        assert!(wait_for_error(&mut tc).to_string().contains("full"));
+
+        for tc in clients.iter_mut() {
+            tc.action_ready().unwrap();
+        }
+
+        // 17 cards each, the player with the 3♦ gets the last card and opens.
+        let mut all_cards: u64 = 0;
+        for tc in clients.iter_mut() {
+            let sm = wait_for_state(tc, network::StateMessageActionType::DEAL);
+            assert_eq!(sm.your_hand.count, 0);
+            let hand = tc.take_hand().unwrap();
+            assert_eq!(all_cards & hand, 0);
+            all_cards |= hand;
+            let has_opening_card = hand & 0x1000 != 0;
+            assert_eq!(hand.count_ones(), if has_opening_card { 18 } else { 17 });
+            assert_eq!(sm.turn == sm.your_index, has_opening_card);
+            assert_eq!(
+                sm.players[sm.your_index as usize].num_cards as u32,
+                hand.count_ones()
+            );
+            assert_eq!(sm.players[3].num_cards, 0);
+        }
+        assert_eq!(all_cards, 0xFFFF_FFFF_FFFF_F000);
+    }
+
+    #[test]
+    fn client_sends_muon_join_without_lobby() {
+        let port: u16 = 27302;
+        let listener = TcpListener::bind(("127.0.0.1", port)).unwrap();
+
+        let mut tc = network::client::TcpClient::connect(format!("127.0.0.1:{}", port)).unwrap();
+        let (mut ts, _) = listener.accept().unwrap();
+
+        // Like the Muon version, this server doesn't answer the table list.
+        assert!(!tc.detect_lobby(Duration::from_millis(100)).unwrap());
+        tc.send_join_msg(&String::from("Kim")).unwrap();
+
+        // Skip the table list request and heartbeats.
+        let mut jm = Vec::new();
+        while jm.first() != Some(&1) {
+            let mut header = [0u8; 8];
+            ts.read_exact(&mut header).unwrap();
+            let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+            jm = header.to_vec();
+            jm.resize(size as usize, 0);
+            ts.read_exact(&mut jm[8..]).unwrap();
+        }
+        assert_eq!(jm.len(), network::JoinMessage::MUON_SIZE);
+        let muon = muon_join_msg(
+            network::common::MAGICNUMBER,
+            network::common::VERSION,
+            "Kim",
+        );
+        assert_eq!(jm, muon);
+    }
+
+    #[test]
+    fn client_rejoins_after_connection_loss() {
+        let port: u16 = 27303;
+        let proxy_port: u16 = 27304;
+        let addr = format!("127.0.0.1:{}", port);
+        let proxy_addr = format!("127.0.0.1:{}", proxy_port);
+        network::server::start(port, 1).unwrap();
+        let connections = proxy(proxy_port, addr);
+
+        let mut clients = Vec::new();
+        for p in 0..4 {
+            let mut tc = connect(&proxy_addr);
+            tc.send_join_msg(&format!("Player{}", p)).unwrap();
+            wait_for_state(&mut tc, network::StateMessageActionType::UPDATE);
+            tc.action_ready().unwrap();
+            clients.push(tc);
+        }
+
+        let mut hands = Vec::new();
+        let mut turn = -1;
+        for tc in clients.iter_mut() {
+            let sm = wait_for_state(tc, network::StateMessageActionType::DEAL);
+            hands.push(sm.your_hand.to_card());
+            turn = sm.turn;
+        }
+
+        // Drop the connection of a player that is not on turn, the client has to find its way
+        // back on its own.
+        let seat = ((turn + 2) & 0x3) as usize;
+        {
+            let list = connections.lock().unwrap();
+            let (client, upstream) = &list[seat];
+            let _ = client.shutdown(Shutdown::Both);
+            let _ = upstream.shutdown(Shutdown::Both);
+        }
+
+        let other = (seat + 1) & 0x3;
+        let mut disconnected = false;
+        let mut rejoined = false;
+        for _ in 0..100 {
+            let sm = wait_for_state(&mut clients[other], network::StateMessageActionType::UPDATE);
+            disconnected |= sm.players[seat].is_disconnected();
+            if disconnected && !sm.players[seat].is_disconnected() {
+                rejoined = true;
+                break;
+            }
+        }
+        assert!(disconnected);
+        assert!(rejoined);
+
+        let tc = &mut clients[seat];
+        loop {
+            let sm = wait_for_state(tc, network::StateMessageActionType::UPDATE);
+            if sm.players[seat].is_disconnected() {
+                continue;
+            }
+            assert_eq!(sm.your_index, seat as i32);
+            assert_eq!(sm.your_hand.to_card(), hands[seat]);
+            assert_eq!(sm.turn, turn);
+            break;
+        }
+        assert_eq!(connections.lock().unwrap().len(), 5);
+    }
+
+    #[test]
+    fn server_waits_for_player_at_bot_table() {
+        let port: u16 = 27305;
+        let addr = format!("127.0.0.1:{}", port);
+        let mut srv = network::server::GameServer::bind(port, 1, 0).unwrap();
+        srv.set_bots(Some(big2rules::bot::Strategy::Lowest), "Bot");
+        srv.spawn().unwrap();
+
+        let mut tc = connect(&addr);
+        let session = tc.session();
+        tc.send_join_msg(&String::from("Human")).unwrap();
+        wait_for_state(&mut tc, network::StateMessageActionType::UPDATE);
+        tc.action_ready().unwrap();
+
+        // The bots play until it is our turn.
+        let mut sm = wait_for_state(&mut tc, network::StateMessageActionType::DEAL);
+        while sm.turn != sm.your_index {
+            match tc.check_buffer().unwrap() {
+                None => thread::sleep(Duration::from_millis(1)),
+                Some(next) => sm = next,
+            }
+        }
+        let hand = sm.your_hand.to_card();
+        network::client::disconnect(tc);
+
+        // The only player at the table left, the bots don't play on and the table isn't reset.
+        thread::sleep(Duration::from_millis(500));
+        let mut tc = network::client::TcpClient::connect_session(addr, session).unwrap();
+        assert!(tc.detect_lobby(Duration::from_secs(5)).unwrap());
+        tc.send_join_msg(&String::from("Human")).unwrap();
+        let sm = wait_for_state(&mut tc, network::StateMessageActionType::UPDATE);
+        assert_eq!(sm.your_index, 0);
+        assert_eq!(sm.round, 1);
+        assert_eq!(sm.turn, 0);
+        assert_eq!(sm.your_hand.to_card(), hand);
+    }
+
+    #[test]
+    fn client_survives_unknown_reject_reason() {
+        let port: u16 = 27307;
+        let listener = TcpListener::bind(("127.0.0.1", port)).unwrap();
+
+        let mut tc = network::client::TcpClient::connect(format!("127.0.0.1:{}", port)).unwrap();
+        let (mut ts, _) = listener.accept().unwrap();
+        tc.send_join_msg(&String::from("Kim")).unwrap();
+
+        // A newer server with a reason this client doesn't know.
+        ts.write_all(&[11, 0, 0, 0, 12, 0, 0, 0, 99, 0, 0, 0])
+            .unwrap();
+        let e = wait_for_error(&mut tc);
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+}